@@ -0,0 +1,142 @@
+use std::{env, fmt::Write, fs, path::Path};
+
+pub const CORPORA: &[&str] = &["twitter.json", "canada.json", "citm_catalog.json"];
+
+// The canonical files from nativejson-benchmark, read from benches/data. A missing
+// file fails the bench unless `JSON_BENCH_SYNTHETIC=1` asks for a deterministic
+// stand-in with the same shape, labelled `synthetic/<name>` so a report never passes
+// one off as the other. Returns the label and the data.
+pub fn corpus(name: &str) -> (String, String) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/data").join(name);
+    if let Ok(data) = fs::read_to_string(&path) {
+        return (name.to_string(), data);
+    }
+    if env::var_os("JSON_BENCH_SYNTHETIC").is_none_or(|v| v != "1") {
+        panic!(
+            "{} is missing, copy it from \
+             https://github.com/miloyip/nativejson-benchmark/tree/master/data \
+             or set JSON_BENCH_SYNTHETIC=1 to bench a synthetic stand-in",
+            path.display()
+        );
+    }
+    let data = match name {
+        "twitter.json" => synthetic_twitter(),
+        "canada.json" => synthetic_canada(),
+        "citm_catalog.json" => synthetic_citm(),
+        _ => unreachable!(),
+    };
+    (format!("synthetic/{}", name), data)
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn float(&mut self) -> f64 {
+        (self.next() % 1_000_000_000) as f64 / 1e7
+    }
+}
+
+// ~100 statuses with nested users, unicode text and escapes
+fn synthetic_twitter() -> String {
+    let mut rng = Rng(1);
+    let mut s = String::from("{\"statuses\": [");
+    for i in 0..100 {
+        if i > 0 {
+            s.push(',');
+        }
+        write!(
+            s,
+            r#"
+  {{
+    "created_at": "Sun Aug 31 00:29:15 +0000 2014",
+    "id": {id},
+    "id_str": "{id}",
+    "text": "@aym0566x \n\n名前:前田あゆみ\n第一印象:なんか怖っ！❤ \"quoted\" https:\/\/t.co\/{i}",
+    "truncated": false,
+    "entities": {{"hashtags": [], "urls": [{{"url": "https:\/\/t.co\/abc", "indices": [{a}, {b}]}}]}},
+    "user": {{
+      "id": {uid},
+      "name": "ちゃん ℃",
+      "screen_name": "user_{i}",
+      "description": "プロフィール 🎵 and some ascii text to make it longer than a tweet",
+      "followers_count": {f},
+      "verified": {v},
+      "profile_background_color": "C0DEED",
+      "default_profile_image": null
+    }},
+    "retweet_count": {r},
+    "favorited": false,
+    "lang": "ja"
+  }}"#,
+            id = 505874924095815681u64 + rng.next() % 1000,
+            i = i,
+            a = rng.next() % 100,
+            b = rng.next() % 140,
+            uid = rng.next() % 3_000_000_000,
+            f = rng.next() % 100_000,
+            v = rng.next() & 1 == 0,
+            r = rng.next() % 1000,
+        )
+        .unwrap();
+    }
+    s.push_str("\n], \"search_metadata\": {\"count\": 100, \"max_id\": 505874924095815681}}");
+    s
+}
+
+// a GeoJSON polygon, almost all floats
+fn synthetic_canada() -> String {
+    let mut rng = Rng(2);
+    let mut s = String::from(
+        r#"{"type":"FeatureCollection","features":[{"type":"Feature","properties":{"name":"Canada"},"geometry":{"type":"Polygon","coordinates":["#,
+    );
+    for ring in 0..40 {
+        if ring > 0 {
+            s.push(',');
+        }
+        s.push('[');
+        for point in 0..1400 {
+            if point > 0 {
+                s.push(',');
+            }
+            write!(s, "[-{:.15},{:.14}]", 50.0 + rng.float(), 40.0 + rng.float()).unwrap();
+        }
+        s.push(']');
+    }
+    s.push_str("]}}]}");
+    s
+}
+
+// wide objects keyed by ids, small integers and short strings
+fn synthetic_citm() -> String {
+    let mut rng = Rng(3);
+    let mut s = String::from("{\"areaNames\": {");
+    for i in 0..200 {
+        if i > 0 {
+            s.push(',');
+        }
+        write!(s, "\"2050{:05}\": \"Area {}\"", i, i).unwrap();
+    }
+    s.push_str("}, \"performances\": [");
+    for i in 0..1000 {
+        if i > 0 {
+            s.push(',');
+        }
+        write!(
+            s,
+            r#"{{"eventId": {}, "id": {}, "logo": null, "name": null, "prices": [{{"amount": {}, "audienceSubCategoryId": 337100890, "seatCategoryId": 338937295}}], "seatCategories": [{{"areas": [{{"areaId": 205705999, "blockIds": []}}], "seatCategoryId": 338937295}}], "start": 1372701600000, "venueCode": "PLEYEL_PLEYEL"}}"#,
+            138586341 + rng.next() % 1000,
+            339887544 + i,
+            rng.next() % 100_000,
+        )
+        .unwrap();
+    }
+    s.push_str("]}");
+    s
+}
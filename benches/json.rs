@@ -0,0 +1,56 @@
+mod corpus;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use corpus::{corpus, CORPORA};
+use json::{parse, parse_borrowed, parse_into_arena};
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for name in CORPORA {
+        let (name, data) = corpus(name);
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_function(name, |b| b.iter(|| parse(black_box(&data)).unwrap()));
+    }
+    group.finish();
+}
+
+fn bench_parse_borrowed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_borrowed");
+    for name in CORPORA {
+        let (name, data) = corpus(name);
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_function(name, |b| b.iter(|| parse_borrowed(black_box(&data)).unwrap()));
+    }
+    group.finish();
+}
+
+fn bench_parse_into_arena(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_into_arena");
+    for name in CORPORA {
+        let (name, data) = corpus(name);
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_function(name, |b| b.iter(|| parse_into_arena(black_box(&data)).unwrap()));
+    }
+    group.finish();
+}
+
+fn bench_stringify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stringify");
+    for name in CORPORA {
+        let (name, data) = corpus(name);
+        let value = parse(&data).unwrap();
+        let len = value.dump().len();
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_function(name, |b| b.iter(|| black_box(&value).dump()));
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_parse,
+    bench_parse_borrowed,
+    bench_parse_into_arena,
+    bench_stringify
+);
+criterion_main!(benches);
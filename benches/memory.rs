@@ -0,0 +1,120 @@
+// Heap used by a parsed document, `JsonValue` against `Arena`. Not a criterion bench:
+// it counts bytes through the global allocator and prints a table.
+//
+//     cargo bench --bench memory
+
+mod corpus;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fmt::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use corpus::{corpus, CORPORA};
+use json::{parse, parse_into_arena};
+
+struct Counting;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(live, Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        let live = LIVE.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(live, Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct Usage {
+    // held by the document once parsed
+    retained: usize,
+    // highest while parsing, above what was live before
+    peak: usize,
+    allocations: usize,
+}
+
+fn measure<T>(parse: impl FnOnce() -> T) -> Usage {
+    let before = LIVE.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let document = parse();
+    let usage = Usage {
+        retained: LIVE.load(Ordering::Relaxed) - before,
+        peak: PEAK.load(Ordering::Relaxed) - before,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+    };
+    drop(document);
+    usage
+}
+
+// the case the arena is for: one big array of objects with the same keys
+fn records() -> String {
+    let mut s = String::from("[");
+    for i in 0..100_000 {
+        if i > 0 {
+            s.push(',');
+        }
+        write!(
+            s,
+            r#"{{"id": {}, "name": "user {}", "email": "u{}@example.com", "active": {}, "score": {}.5, "tags": ["a", "b"]}}"#,
+            i,
+            i,
+            i,
+            i % 2 == 0,
+            i % 1000
+        )
+        .unwrap();
+    }
+    s.push(']');
+    s
+}
+
+fn mib(bytes: usize) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn main() {
+    let mut inputs: Vec<(String, String)> = CORPORA.iter().map(|name| corpus(name)).collect();
+    inputs.push(("synthetic/records.json".to_string(), records()));
+
+    println!(
+        "| corpus | size | tree retained | arena retained | tree peak | arena peak \
+         | tree allocs | arena allocs |"
+    );
+    println!("|---|---|---|---|---|---|---|---|");
+    for (name, data) in &inputs {
+        let tree = measure(|| parse(data).unwrap());
+        let arena = measure(|| parse_into_arena(data).unwrap());
+        println!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} |",
+            name,
+            mib(data.len()),
+            mib(tree.retained),
+            mib(arena.retained),
+            mib(tree.peak),
+            mib(arena.peak),
+            tree.allocations,
+            arena.allocations
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use syn::{Attribute, ExprPath, LitStr, Result};
+
+use crate::case::RenameRule;
+
+pub enum Default {
+    None,
+    Trait,
+    Path(ExprPath),
+}
+
+pub enum Tagging {
+    External,
+    Internal(String),
+    Adjacent(String, String),
+    Untagged,
+}
+
+// #[json(rename_all = "camelCase", default, tag = "type", content = "data", untagged)]
+pub struct Container {
+    pub rename_all: RenameRule,
+    pub default: Default,
+    pub tagging: Tagging,
+}
+
+// #[json(rename = "name", rename_all = "camelCase")]
+pub struct Variant {
+    pub rename: Option<String>,
+    pub rename_all: Option<RenameRule>,
+}
+
+// #[json(rename = "name", default, default = "path", skip, flatten)]
+pub struct Field {
+    pub rename: Option<String>,
+    pub default: Default,
+    pub skip: bool,
+    pub flatten: bool,
+}
+
+fn parse_rule(lit: &LitStr) -> Result<RenameRule> {
+    RenameRule::from_str(&lit.value())
+        .ok_or_else(|| syn::Error::new(lit.span(), format!("unknown rename rule `{}`", lit.value())))
+}
+
+impl Container {
+    pub fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut container = Container {
+            rename_all: RenameRule::None,
+            default: Default::None,
+            tagging: Tagging::External,
+        };
+        let mut tag = None;
+        let mut content = None;
+        let mut untagged = false;
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("json")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename_all") {
+                    container.rename_all = parse_rule(&meta.value()?.parse()?)?;
+                } else if meta.path.is_ident("default") {
+                    container.default = Default::Trait;
+                } else if meta.path.is_ident("tag") {
+                    tag = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("content") {
+                    content = Some(meta.value()?.parse::<LitStr>()?);
+                } else if meta.path.is_ident("untagged") {
+                    untagged = true;
+                } else {
+                    return Err(meta.error("unknown json container attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        container.tagging = match (tag, content, untagged) {
+            (None, None, false) => Tagging::External,
+            (Some(tag), None, false) => Tagging::Internal(tag),
+            (Some(tag), Some(content), false) => Tagging::Adjacent(tag, content.value()),
+            (None, None, true) => Tagging::Untagged,
+            (None, Some(content), _) => {
+                return Err(syn::Error::new(content.span(), "`content` requires `tag`"))
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    "`untagged` cannot be combined with `tag`",
+                ))
+            }
+        };
+        Ok(container)
+    }
+}
+
+impl Variant {
+    pub fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut variant = Variant {
+            rename: None,
+            rename_all: None,
+        };
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("json")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    variant.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("rename_all") {
+                    variant.rename_all = Some(parse_rule(&meta.value()?.parse()?)?);
+                } else {
+                    return Err(meta.error("unknown json variant attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(variant)
+    }
+}
+
+impl Field {
+    pub fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut field = Field {
+            rename: None,
+            default: Default::None,
+            skip: false,
+            flatten: false,
+        };
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("json")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    field.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    field.default = if meta.input.peek(syn::Token![=]) {
+                        Default::Path(meta.value()?.parse::<LitStr>()?.parse()?)
+                    } else {
+                        Default::Trait
+                    };
+                } else if meta.path.is_ident("skip") {
+                    field.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    field.flatten = true;
+                } else {
+                    return Err(meta.error("unknown json field attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(field)
+    }
+}
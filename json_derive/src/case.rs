@@ -0,0 +1,78 @@
+// rename_all rules, fields are written in snake_case and variants in PascalCase
+#[derive(Clone, Copy)]
+pub enum RenameRule {
+    None,
+    LowerCase,
+    UpperCase,
+    PascalCase,
+    CamelCase,
+    SnakeCase,
+    ScreamingSnakeCase,
+    KebabCase,
+}
+
+impl RenameRule {
+    pub fn from_str(rule: &str) -> Option<Self> {
+        Some(match rule {
+            "lowercase" => RenameRule::LowerCase,
+            "UPPERCASE" => RenameRule::UpperCase,
+            "PascalCase" => RenameRule::PascalCase,
+            "camelCase" => RenameRule::CamelCase,
+            "snake_case" => RenameRule::SnakeCase,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnakeCase,
+            "kebab-case" => RenameRule::KebabCase,
+            _ => return None,
+        })
+    }
+
+    pub fn apply(self, name: &str) -> String {
+        let words = split_words(name);
+        match self {
+            RenameRule::None => name.to_string(),
+            RenameRule::LowerCase => words.concat().to_lowercase(),
+            RenameRule::UpperCase => words.concat().to_uppercase(),
+            RenameRule::PascalCase => words.iter().map(|w| capitalize(w)).collect(),
+            RenameRule::CamelCase => {
+                let pascal: String = words.iter().map(|w| capitalize(w)).collect();
+                let mut chars = pascal.chars();
+                match chars.next() {
+                    Some(first) => first.to_lowercase().chain(chars).collect(),
+                    None => pascal,
+                }
+            }
+            RenameRule::SnakeCase => words.join("_").to_lowercase(),
+            RenameRule::ScreamingSnakeCase => words.join("_").to_uppercase(),
+            RenameRule::KebabCase => words.join("-").to_lowercase(),
+        }
+    }
+}
+
+// "user_name" -> ["user", "name"], "UserName" -> ["User", "Name"]
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for ch in name.chars() {
+        if ch == '_' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if ch.is_uppercase() && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        current.push(ch);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }
+}
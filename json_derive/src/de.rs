@@ -0,0 +1,234 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DataEnum, DeriveInput, Error, Fields, Result};
+
+use crate::{
+    attr::{Container, Default, Tagging, Variant},
+    case::RenameRule,
+    fields, is_newtype, variant_name, with_bound, Field,
+};
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let container = Container::from_attrs(&input.attrs)?;
+    let ident = &input.ident;
+    let generics = with_bound(&input.generics, quote!(::json::FromJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields = fields(&data.fields, container.rename_all)?;
+            let container_default = matches!(container.default, Default::Trait);
+            de_fields(quote!(Self), &data.fields, &fields, container_default)
+        }
+        Data::Enum(_) if matches!(container.default, Default::Trait) => {
+            return Err(Error::new_spanned(ident, "`#[json(default)]` is not supported on enums"))
+        }
+        Data::Enum(data) => de_enum(data, &container, &ident.to_string())?,
+        Data::Union(_) => return Err(Error::new_spanned(input, "FromJson cannot be derived for unions")),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::json::FromJson for #ident #ty_generics #where_clause {
+            fn from_json(value: &::json::JsonValue) -> ::json::JsonResult<Self> {
+                #body
+            }
+        }
+    })
+}
+
+// expands to a block of type `JsonResult<Self>` reading from `value`
+fn de_fields(ctor: TokenStream, data: &Fields, fields: &[Field], container_default: bool) -> TokenStream {
+    match data {
+        Fields::Named(_) => de_named(ctor, fields, container_default),
+        Fields::Unnamed(_) if is_newtype(fields) => {
+            quote!(::json::FromJson::from_json(value).map(#ctor))
+        }
+        Fields::Unnamed(_) => {
+            // skipped fields aren't in the array, the others keep their order
+            let len = fields.iter().filter(|field| !field.attrs.skip).count();
+            let expected = format!("array of {} elements", len);
+            let mut i = 0usize;
+            let items: Vec<_> = fields
+                .iter()
+                .map(|field| {
+                    if field.attrs.skip {
+                        return match &field.attrs.default {
+                            Default::Path(path) => quote!(#path()),
+                            _ => quote!(::std::default::Default::default()),
+                        };
+                    }
+                    let index = i;
+                    i += 1;
+                    quote!(::json::FromJson::from_json(&array[#index]).map_err(|e| e.at_index(#index))?)
+                })
+                .collect();
+            quote! {
+                match value {
+                    ::json::JsonValue::Array(array) if array.len() == #len => Ok(#ctor(#(#items),*)),
+                    other => Err(::json::JsonError::expected(#expected, other)),
+                }
+            }
+        }
+        Fields::Unit => quote! {
+            match value {
+                ::json::JsonValue::Null => Ok(#ctor),
+                other => Err(::json::JsonError::expected("null", other)),
+            }
+        },
+    }
+}
+
+fn de_named(ctor: TokenStream, fields: &[Field], container_default: bool) -> TokenStream {
+    let default = if container_default {
+        quote!(let __default: Self = ::std::default::Default::default();)
+    } else {
+        quote!()
+    };
+
+    let inits = fields.iter().map(|field| {
+        let member = &field.member;
+        let ty = field.ty;
+        let name = &field.name;
+
+        let missing = match &field.attrs.default {
+            Default::Trait => quote!(::std::default::Default::default()),
+            Default::Path(path) => quote!(#path()),
+            Default::None if container_default => quote!(__default.#member),
+            Default::None if field.attrs.skip => quote!(::std::default::Default::default()),
+            Default::None => quote!(<#ty as ::json::FromJson>::missing_field(#name)?),
+        };
+
+        let init = if field.attrs.skip {
+            missing
+        } else if field.attrs.flatten {
+            quote!(::json::FromJson::from_json(value)?)
+        } else {
+            quote! {
+                match object.get(#name) {
+                    Some(field) => ::json::FromJson::from_json(field).map_err(|e| e.at_field(#name))?,
+                    None => #missing,
+                }
+            }
+        };
+        quote!(#member: #init)
+    });
+
+    quote! {{
+        #[allow(unused_variables)]
+        let object = match value {
+            ::json::JsonValue::Object(object) => object,
+            other => return Err(::json::JsonError::expected("object", other)),
+        };
+        #default
+        Ok(#ctor { #(#inits),* })
+    }}
+}
+
+fn de_enum(data: &DataEnum, container: &Container, enum_name: &str) -> Result<TokenStream> {
+    let mut arms = Vec::new();
+    let mut names = Vec::new();
+
+    for variant in &data.variants {
+        let attrs = Variant::from_attrs(&variant.attrs)?;
+        let name = variant_name(variant, &attrs, container.rename_all);
+        let ident = &variant.ident;
+        let fields = fields(&variant.fields, attrs.rename_all.unwrap_or(RenameRule::None))?;
+        let is_unit = matches!(variant.fields, Fields::Unit);
+        let content = de_fields(quote!(Self::#ident), &variant.fields, &fields, false);
+        // keep `?` and early returns of the content scoped to this variant
+        let content = quote!((|| -> ::json::JsonResult<Self> { #content })());
+
+        let arm = match &container.tagging {
+            Tagging::External if is_unit => quote!(Ok(Self::#ident)),
+            Tagging::External => quote!(#content.map_err(|e| e.at_field(#name))),
+            Tagging::Internal(_) if is_unit => quote!(Ok(Self::#ident)),
+            Tagging::Internal(_) => content,
+            Tagging::Adjacent(_, _) if is_unit => quote!(Ok(Self::#ident)),
+            Tagging::Adjacent(_, content_key) => quote! {
+                match object.get(#content_key) {
+                    Some(value) => #content.map_err(|e| e.at_field(#content_key)),
+                    None => Err(::json::JsonError::undefined_field(#content_key.to_string())),
+                }
+            },
+            Tagging::Untagged => content,
+        };
+
+        names.push(name);
+        arms.push((is_unit, arm));
+    }
+
+    let expected = names.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", ");
+    let unknown = quote! {
+        other => Err(::json::JsonError::invalid_type(format!("expected one of {}, found `{}`", #expected, other)))
+    };
+
+    let tag_value = |tag: &str| {
+        quote! {
+            let object = match value {
+                ::json::JsonValue::Object(object) => object,
+                other => return Err(::json::JsonError::expected("object", other)),
+            };
+            let name = match object.get(#tag) {
+                Some(::json::JsonValue::String(name)) => name,
+                Some(other) => return Err(::json::JsonError::expected("string", other).at_field(#tag)),
+                None => return Err(::json::JsonError::undefined_field(#tag.to_string())),
+            };
+        }
+    };
+
+    Ok(match &container.tagging {
+        Tagging::External => {
+            let unit_arms = names
+                .iter()
+                .zip(&arms)
+                .filter(|(_, (is_unit, _))| *is_unit)
+                .map(|(name, (_, arm))| quote!(#name => #arm));
+            let content_arms = names
+                .iter()
+                .zip(&arms)
+                .filter(|(_, (is_unit, _))| !*is_unit)
+                .map(|(name, (_, arm))| quote!(#name => #arm));
+            quote! {
+                match value {
+                    ::json::JsonValue::String(name) => match name.as_str() {
+                        #(#unit_arms,)*
+                        #unknown,
+                    },
+                    ::json::JsonValue::Object(object) if object.len() == 1 => {
+                        let (name, value) = object.iter().next().unwrap();
+                        match name.as_str() {
+                            #(#content_arms,)*
+                            #unknown,
+                        }
+                    }
+                    other => Err(::json::JsonError::expected("string or object with a single key", other)),
+                }
+            }
+        }
+        Tagging::Internal(tag) | Tagging::Adjacent(tag, _) => {
+            let tag_value = tag_value(tag);
+            let arms = names.iter().zip(&arms).map(|(name, (_, arm))| quote!(#name => #arm));
+            quote! {
+                #tag_value
+                match name.as_str() {
+                    #(#arms,)*
+                    #unknown,
+                }
+            }
+        }
+        Tagging::Untagged => {
+            let attempts = arms.iter().map(|(_, arm)| {
+                quote! {
+                    if let Ok(result) = #arm {
+                        return Ok(result);
+                    }
+                }
+            });
+            let message = format!("expected data matching a variant of untagged enum `{}`", enum_name);
+            quote! {
+                #(#attempts)*
+                Err(::json::JsonError::invalid_type(#message.to_string()))
+            }
+        }
+    })
+}
@@ -0,0 +1,127 @@
+//! `#[derive(ToJson, FromJson)]` for the `json` crate.
+//!
+//! ```ignore
+//! #[derive(ToJson, FromJson)]
+//! #[json(rename_all = "camelCase")]
+//! struct User {
+//!     user_name: String,
+//!     #[json(default)]
+//!     age: u32,
+//! }
+//! ```
+//!
+//! Container attributes: `rename_all`, `default`, `tag`, `content`, `untagged`.
+//! Variant attributes: `rename`, `rename_all`.
+//! Field attributes: `rename`, `default`, `default = "path"`, `skip`, `flatten`.
+//!
+//! `to_json` panics when an internally tagged variant or a flattened field serializes
+//! to anything but an object (or, for a flattened field, null), since there is
+//! nowhere to put the tag or the fields. A container `default` is for structs only:
+//!
+//! ```compile_fail
+//! #[derive(json_derive::FromJson)]
+//! #[json(default)]
+//! enum Mode {
+//!     Fast,
+//! }
+//! ```
+//!
+//! `json_str!` checks a json string literal at compile time:
+//!
+//! ```ignore
+//! let fixture = json_str!(r#"{"users": [{"name": "ada"}]}"#);
+//! ```
+
+mod attr;
+mod case;
+mod de;
+mod literal;
+mod ser;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{parse_macro_input, parse_quote, DeriveInput, Fields, Generics, Member, Type};
+
+#[proc_macro_derive(ToJson, attributes(json))]
+pub fn derive_to_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    ser::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn derive_from_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    de::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// A `json::JsonValue` from a string literal that is parsed while compiling, so a
+/// syntax error fails the build instead of the test:
+///
+/// ```compile_fail
+/// let value = json_derive::json_str!(r#"{"a": [1, 2,]}"#);
+/// ```
+#[proc_macro]
+pub fn json_str(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::LitStr);
+    literal::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+pub(crate) struct Field<'a> {
+    member: Member,
+    ty: &'a Type,
+    name: String,
+    attrs: attr::Field,
+}
+
+pub(crate) fn fields(fields: &Fields, rule: case::RenameRule) -> syn::Result<Vec<Field<'_>>> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let attrs = attr::Field::from_attrs(&field.attrs)?;
+            let (member, name) = match &field.ident {
+                Some(ident) => {
+                    let ident_name = ident.to_string();
+                    let ident_name = ident_name.trim_start_matches("r#");
+                    let name = attrs.rename.clone().unwrap_or_else(|| rule.apply(ident_name));
+                    (Member::Named(ident.clone()), name)
+                }
+                None => (Member::Unnamed(i.into()), i.to_string()),
+            };
+            Ok(Field {
+                member,
+                ty: &field.ty,
+                name,
+                attrs,
+            })
+        })
+        .collect()
+}
+
+// a tuple struct or variant of one field that isn't skipped, written as that field
+pub(crate) fn is_newtype(fields: &[Field]) -> bool {
+    fields.len() == 1 && !fields[0].attrs.skip
+}
+
+pub(crate) fn variant_name(variant: &syn::Variant, attrs: &attr::Variant, rule: case::RenameRule) -> String {
+    attrs
+        .rename
+        .clone()
+        .unwrap_or_else(|| rule.apply(&variant.ident.to_string()))
+}
+
+pub(crate) fn with_bound(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<_> = generics.type_params().map(|param| param.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote!(#param: #bound));
+    }
+    generics
+}
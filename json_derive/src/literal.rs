@@ -0,0 +1,100 @@
+use json::{parse_complete, JsonError, JsonValue, Number, Position};
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::quote;
+use syn::{Error, LitStr, Result};
+
+// Parse the literal with the json crate itself and write out the value it gives, so
+// it accepts what `parse_complete` does and the last of duplicate keys wins. An error
+// is reported at the offending token where the compiler can point inside a literal,
+// on the whole literal otherwise, with the line and column in the message.
+pub fn expand(lit: &LitStr) -> Result<TokenStream> {
+    let source = lit.value();
+    let error = match parse_complete(&source) {
+        Ok(value) => return Ok(construct(&value)),
+        Err(error) => error,
+    };
+    let offset = match error {
+        JsonError::UnexpectedEndOfJson => Some(source.len()),
+        ref error => error.offset(),
+    };
+    let (span, message) = match offset {
+        Some(offset) => (
+            token_span(lit, &source, offset).unwrap_or_else(|| lit.span()),
+            format!("invalid json at {}: {}", Position::locate(&source, offset), error),
+        ),
+        None => (lit.span(), format!("invalid json: {}", error)),
+    };
+    Err(Error::new(span, message))
+}
+
+// The span of the character at `offset` of the value, the closing quote past its end.
+// Only a literal that spells the value out byte for byte (a raw string, or one without
+// escapes) can be mapped, and only nightly compilers hand out spans inside literals.
+fn token_span(lit: &LitStr, source: &str, offset: usize) -> Option<Span> {
+    let token = lit.token();
+    let repr = token.to_string();
+    let start = repr.find('"')? + 1;
+    if repr.get(start..start + source.len()) != Some(source) {
+        return None;
+    }
+    let len = source[offset..].chars().next().map_or(1, char::len_utf8);
+    token.subspan(start + offset..start + offset + len)
+}
+
+fn construct(value: &JsonValue) -> TokenStream {
+    match value {
+        JsonValue::Null => quote!(::json::JsonValue::Null),
+        JsonValue::Boolen(b) => quote!(::json::JsonValue::Boolen(#b)),
+        JsonValue::String(s) => quote!(::json::JsonValue::String(::std::string::String::from(#s))),
+        // only `SurrogatePolicy::Preserve` makes these
+        JsonValue::Wtf8(s) => {
+            let s = s.to_string_lossy();
+            quote!(::json::JsonValue::String(::std::string::String::from(#s)))
+        }
+        // only `parse_with_raw` makes these
+        JsonValue::Raw(s) => quote!(::json::JsonValue::Raw(::std::string::String::from(#s))),
+        JsonValue::Number(n) => {
+            let n = number(n);
+            quote!(::json::JsonValue::Number(#n))
+        }
+        JsonValue::Array(items) => {
+            let items = items.iter().map(construct);
+            quote!(::json::JsonValue::Array(::std::vec![#(#items),*]))
+        }
+        JsonValue::Object(object) => {
+            let keys = object.keys();
+            let values = object.values().map(construct);
+            quote!(::json::JsonValue::Object({
+                let mut object = ::std::collections::BTreeMap::new();
+                #(object.insert(::std::string::String::from(#keys), #values);)*
+                object
+            }))
+        }
+    }
+}
+
+fn number(n: &Number) -> TokenStream {
+    match *n {
+        Number::Integer(n) => {
+            let n = Literal::i64_suffixed(n);
+            quote!(::json::Number::Integer(#n))
+        }
+        Number::Unsigned(n) => {
+            let n = Literal::u64_suffixed(n);
+            quote!(::json::Number::Unsigned(#n))
+        }
+        Number::BigInteger(high, low) => {
+            let (high, low) = (Literal::i64_suffixed(high), Literal::u64_suffixed(low));
+            quote!(::json::Number::BigInteger(#high, #low))
+        }
+        // 1e999 overflows to infinity
+        Number::Float(f) if f.is_infinite() => match f > 0.0 {
+            true => quote!(::json::Number::Float(f64::INFINITY)),
+            false => quote!(::json::Number::Float(f64::NEG_INFINITY)),
+        },
+        Number::Float(f) => {
+            let f = Literal::f64_suffixed(f);
+            quote!(::json::Number::Float(#f))
+        }
+    }
+}
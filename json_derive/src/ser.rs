@@ -0,0 +1,182 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DataEnum, DeriveInput, Error, Fields, Result};
+
+use crate::{
+    attr::{Container, Tagging, Variant},
+    case::RenameRule,
+    fields, is_newtype, variant_name, with_bound, Field,
+};
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let container = Container::from_attrs(&input.attrs)?;
+    let ident = &input.ident;
+    let generics = with_bound(&input.generics, quote!(::json::ToJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => ser_struct(&data.fields, container.rename_all, &ident.to_string())?,
+        Data::Enum(data) => ser_enum(data, &container, &ident.to_string())?,
+        Data::Union(_) => return Err(Error::new_spanned(input, "ToJson cannot be derived for unions")),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::json::ToJson for #ident #ty_generics #where_clause {
+            fn to_json(&self) -> ::json::JsonValue {
+                #body
+            }
+        }
+    })
+}
+
+fn ser_struct(data: &Fields, rule: RenameRule, owner: &str) -> Result<TokenStream> {
+    let fields = fields(data, rule)?;
+    let accessors: Vec<_> = fields
+        .iter()
+        .map(|field| {
+            let member = &field.member;
+            quote!(&self.#member)
+        })
+        .collect();
+    Ok(ser_fields(data, &fields, &accessors, owner))
+}
+
+// accessors evaluate to `&T` for every field, `owner` names the type in panics
+fn ser_fields(
+    data: &Fields,
+    fields: &[Field],
+    accessors: &[TokenStream],
+    owner: &str,
+) -> TokenStream {
+    match data {
+        Fields::Named(_) => ser_named(fields, accessors, owner),
+        Fields::Unnamed(_) if is_newtype(fields) => {
+            let accessor = &accessors[0];
+            quote!(::json::ToJson::to_json(#accessor))
+        }
+        Fields::Unnamed(_) => {
+            let items = fields
+                .iter()
+                .zip(accessors)
+                .filter(|(field, _)| !field.attrs.skip)
+                .map(|(_, accessor)| quote!(::json::ToJson::to_json(#accessor)));
+            quote!(::json::JsonValue::Array(vec![#(#items),*]))
+        }
+        Fields::Unit => quote!(::json::JsonValue::Null),
+    }
+}
+
+fn ser_named(fields: &[Field], accessors: &[TokenStream], owner: &str) -> TokenStream {
+    let inserts = fields
+        .iter()
+        .zip(accessors)
+        .filter(|(field, _)| !field.attrs.skip)
+        .map(|(field, accessor)| {
+            let name = &field.name;
+            if field.attrs.flatten {
+                let message = format!("flattened field `{}::{}` must serialize to an object", owner, name);
+                // null is a `None`, which flattens to nothing
+                quote! {
+                    match ::json::ToJson::to_json(#accessor) {
+                        ::json::JsonValue::Object(inner) => object.extend(inner),
+                        ::json::JsonValue::Null => (),
+                        other => panic!("{}, found {}", #message, other.type_name()),
+                    }
+                }
+            } else {
+                quote! {
+                    object.insert(#name.to_string(), ::json::ToJson::to_json(#accessor));
+                }
+            }
+        });
+
+    quote! {{
+        #[allow(unused_mut)]
+        let mut object = ::std::collections::BTreeMap::new();
+        #(#inserts)*
+        ::json::JsonValue::Object(object)
+    }}
+}
+
+fn ser_enum(data: &DataEnum, container: &Container, enum_name: &str) -> Result<TokenStream> {
+    let mut arms = Vec::new();
+
+    for variant in &data.variants {
+        let attrs = Variant::from_attrs(&variant.attrs)?;
+        let name = variant_name(variant, &attrs, container.rename_all);
+        let ident = &variant.ident;
+        let fields = fields(&variant.fields, attrs.rename_all.unwrap_or(RenameRule::None))?;
+        let bindings: Vec<_> = (0..fields.len()).map(|i| format_ident!("__field{}", i)).collect();
+        let accessors: Vec<_> = bindings.iter().map(|binding| quote!(#binding)).collect();
+
+        let pattern = match &variant.fields {
+            Fields::Named(_) => {
+                let members = fields.iter().map(|field| &field.member);
+                quote!(Self::#ident { #(#members: #bindings),* })
+            }
+            Fields::Unnamed(_) => quote!(Self::#ident(#(#bindings),*)),
+            Fields::Unit => quote!(Self::#ident),
+        };
+        let owner = format!("{}::{}", enum_name, ident);
+        let content = ser_fields(&variant.fields, &fields, &accessors, &owner);
+        let is_unit = matches!(variant.fields, Fields::Unit);
+
+        let body = match &container.tagging {
+            Tagging::External if is_unit => quote!(::json::JsonValue::String(#name.to_string())),
+            Tagging::External => quote! {{
+                let mut object = ::std::collections::BTreeMap::new();
+                object.insert(#name.to_string(), #content);
+                ::json::JsonValue::Object(object)
+            }},
+            Tagging::Internal(tag) => {
+                if matches!(variant.fields, Fields::Unnamed(_)) && fields.len() != 1 {
+                    return Err(Error::new_spanned(
+                        variant,
+                        "internally tagged enums cannot contain tuple variants",
+                    ));
+                }
+                let content = if is_unit {
+                    quote!(::json::JsonValue::Object(::std::collections::BTreeMap::new()))
+                } else {
+                    content
+                };
+                // the tag has nowhere to go in anything but an object
+                let message = format!("internally tagged `{}` must serialize to an object", owner);
+                quote! {
+                    match #content {
+                        ::json::JsonValue::Object(mut object) => {
+                            object.insert(#tag.to_string(), ::json::JsonValue::String(#name.to_string()));
+                            ::json::JsonValue::Object(object)
+                        }
+                        other => panic!("{}, found {}", #message, other.type_name()),
+                    }
+                }
+            }
+            Tagging::Adjacent(tag, content_key) => {
+                let content = if is_unit {
+                    quote!()
+                } else {
+                    quote!(object.insert(#content_key.to_string(), #content);)
+                };
+                quote! {{
+                    let mut object = ::std::collections::BTreeMap::new();
+                    object.insert(#tag.to_string(), ::json::JsonValue::String(#name.to_string()));
+                    #content
+                    ::json::JsonValue::Object(object)
+                }}
+            }
+            Tagging::Untagged => content,
+        };
+
+        arms.push(quote!(#pattern => #body));
+    }
+
+    if arms.is_empty() {
+        return Ok(quote!(match *self {}));
+    }
+    Ok(quote! {
+        match self {
+            #(#arms,)*
+        }
+    })
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+
+use json::{array, object, parse, FromJson, JsonError, JsonValue, ToJson};
+use json_derive::{FromJson, ToJson};
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+#[json(rename_all = "camelCase")]
+struct User {
+    user_name: String,
+    #[json(rename = "years")]
+    age: u32,
+    #[json(default)]
+    tags: Vec<String>,
+    nickname: Option<String>,
+    #[json(skip)]
+    session: u64,
+    #[json(flatten)]
+    address: Address,
+}
+
+#[derive(Debug, Default, PartialEq, ToJson, FromJson)]
+struct Address {
+    city: String,
+    #[json(default = "default_zip")]
+    zip: String,
+}
+
+fn default_zip() -> String {
+    "00000".to_string()
+}
+
+#[derive(Debug, Default, PartialEq, ToJson, FromJson)]
+#[json(default)]
+struct Limits {
+    min: i32,
+    max: i32,
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Point(i32, i32);
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Meters(f64);
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Sparse(i32, #[json(skip)] i32, i32, #[json(skip, default = "seven")] i32);
+
+fn seven() -> i32 {
+    7
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+enum External {
+    Unit,
+    Newtype(i32),
+    Tuple(i32, String),
+    Struct { a: bool },
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+#[json(tag = "type", rename_all = "snake_case")]
+enum Internal {
+    Ping,
+    Move { x: i32, y: i32 },
+    Named(Address),
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+#[json(tag = "t", content = "c")]
+enum Adjacent {
+    Empty,
+    Value(String),
+    #[json(rename = "pair", rename_all = "UPPERCASE")]
+    Pair { left: i32, right: i32 },
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+#[json(untagged)]
+enum Untagged {
+    Nothing,
+    Number(i64),
+    Text(String),
+    Point { x: i32, y: i32 },
+}
+
+fn round_trip<T: ToJson + FromJson + PartialEq + std::fmt::Debug>(value: T, json: JsonValue) {
+    assert_eq!(value.to_json(), json);
+    assert_eq!(T::from_json(&json).unwrap(), value);
+}
+
+#[test]
+fn struct_attributes() {
+    let user = User {
+        user_name: "abc".to_string(),
+        age: 20,
+        tags: vec!["a".to_string()],
+        nickname: None,
+        session: 0,
+        address: Address {
+            city: "x".to_string(),
+            zip: "123".to_string(),
+        },
+    };
+    let json = object! {
+        "userName" => "abc",
+        "years" => 20,
+        "tags" => array!["a"],
+        "nickname" => JsonValue::Null,
+        "city" => "x",
+        "zip" => "123"
+    };
+    round_trip(user, json);
+
+    let parsed = User::from_json(&parse(r#"{"userName": "n", "years": 1, "city": "c"}"#).unwrap()).unwrap();
+    assert_eq!(parsed.tags, Vec::<String>::new());
+    assert_eq!(parsed.nickname, None);
+    assert_eq!(parsed.address.zip, "00000");
+}
+
+#[test]
+fn container_default() {
+    assert_eq!(
+        Limits::from_json(&object! {"max" => 10}).unwrap(),
+        Limits { min: 0, max: 10 }
+    );
+}
+
+#[test]
+fn tuple_and_newtype_structs() {
+    round_trip(Point(1, 2), array![1, 2]);
+    round_trip(Meters(1.5), 1.5.into());
+    round_trip(Wrapper { inner: Point(3, 4) }, object! {"inner" => array![3, 4]});
+    assert_eq!(
+        Point::from_json(&array![1]),
+        Err(JsonError::InvalidType("expected array of 2 elements, found array".to_string()))
+    );
+
+    assert_eq!(Sparse(1, 2, 3, 4).to_json(), array![1, 3]);
+    assert_eq!(Sparse::from_json(&array![1, 3]), Ok(Sparse(1, 0, 3, 7)));
+}
+
+#[test]
+#[should_panic(expected = "internally tagged `Counter::Count` must serialize to an object, found number")]
+fn internally_tagged_scalar() {
+    #[derive(ToJson)]
+    #[json(tag = "type")]
+    enum Counter {
+        Count(i32),
+    }
+    Counter::Count(5).to_json();
+}
+
+#[test]
+#[should_panic(expected = "flattened field `Labelled::label` must serialize to an object, found string")]
+fn flattened_scalar() {
+    #[derive(ToJson)]
+    struct Labelled {
+        #[json(flatten)]
+        label: String,
+    }
+    Labelled { label: "x".to_string() }.to_json();
+}
+
+#[test]
+fn externally_tagged() {
+    round_trip(External::Unit, "Unit".into());
+    round_trip(External::Newtype(1), object! {"Newtype" => 1});
+    round_trip(External::Tuple(1, "a".to_string()), object! {"Tuple" => array![1, "a"]});
+    round_trip(External::Struct { a: true }, object! {"Struct" => object! {"a" => true}});
+    assert_eq!(
+        External::from_json(&"Other".into()),
+        Err(JsonError::InvalidType(
+            "expected one of `Unit`, `Newtype`, `Tuple`, `Struct`, found `Other`".to_string()
+        ))
+    );
+}
+
+#[test]
+fn internally_tagged() {
+    round_trip(Internal::Ping, object! {"type" => "ping"});
+    round_trip(Internal::Move { x: 1, y: 2 }, object! {"type" => "move", "x" => 1, "y" => 2});
+    round_trip(
+        Internal::Named(Address { city: "c".to_string(), zip: "z".to_string() }),
+        object! {"type" => "named", "city" => "c", "zip" => "z"},
+    );
+    assert_eq!(
+        Internal::from_json(&object! {"x" => 1}),
+        Err(JsonError::UndefinedField("type".to_string()))
+    );
+}
+
+#[test]
+fn adjacently_tagged() {
+    round_trip(Adjacent::Empty, object! {"t" => "Empty"});
+    round_trip(Adjacent::Value("v".to_string()), object! {"t" => "Value", "c" => "v"});
+    round_trip(
+        Adjacent::Pair { left: 1, right: 2 },
+        object! {"t" => "pair", "c" => object! {"LEFT" => 1, "RIGHT" => 2}},
+    );
+}
+
+#[test]
+fn untagged() {
+    round_trip(Untagged::Nothing, JsonValue::Null);
+    round_trip(Untagged::Number(3), 3.into());
+    round_trip(Untagged::Text("t".to_string()), "t".into());
+    round_trip(Untagged::Point { x: 1, y: 2 }, object! {"x" => 1, "y" => 2});
+    assert_eq!(
+        Untagged::from_json(&array![]),
+        Err(JsonError::InvalidType(
+            "expected data matching a variant of untagged enum `Untagged`".to_string()
+        ))
+    );
+}
+
+#[test]
+fn errors_carry_field_paths() {
+    #[derive(Debug, ToJson, FromJson)]
+    struct Outer {
+        users: Vec<Wrapper<User>>,
+        limits: BTreeMap<String, Limits>,
+    }
+
+    let missing = parse(r#"{"users": [{"inner": {"years": 1, "city": "c"}}], "limits": {}}"#).unwrap();
+    assert_eq!(
+        Outer::from_json(&missing).unwrap_err(),
+        JsonError::UndefinedField("users[0].inner.userName".to_string())
+    );
+
+    let invalid = parse(r#"{"users": [], "limits": {"a": {"min": "1"}}}"#).unwrap();
+    assert_eq!(
+        Outer::from_json(&invalid).unwrap_err(),
+        JsonError::InvalidType("limits.a.min: expected i32, found string".to_string())
+    );
+}
@@ -0,0 +1,32 @@
+use json::{array, object, parse, JsonValue, Number};
+use json_derive::json_str;
+
+#[test]
+fn literal_values() {
+    let value = json_str!(
+        r#"{
+            "name": "ada é\n",
+            "n": [0, -1, 2.5, -1e-3, 170141183460469231731687303715884105727, 1e999],
+            "ok": true,
+            "none": null,
+            "nested": {"a": [], "b": {}}
+        }"#
+    );
+    let expected = parse(
+        r#"{"name": "ada é\n",
+            "n": [0, -1, 2.5, -1e-3, 170141183460469231731687303715884105727, 1e999],
+            "ok": true, "none": null, "nested": {"a": [], "b": {}}}"#,
+    )
+    .unwrap();
+    assert_eq!(value, expected);
+    assert_eq!(value.pointer("/n/5"), Some(&JsonValue::Number(Number::Float(f64::INFINITY))));
+
+    assert_eq!(json_str!("[1, \"2\"]"), array![1, "2"]);
+    assert_eq!(json_str!(r#"{"k": 1, "k": 2}"#), object! {"k" => 2});
+}
+
+// on a stable compiler the span is the whole literal, the message has the position
+#[test]
+fn errors() {
+    trybuild::TestCases::new().compile_fail("tests/ui/json_str_*.rs");
+}
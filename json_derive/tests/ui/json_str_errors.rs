@@ -0,0 +1,8 @@
+use json_derive::json_str;
+
+fn main() {
+    let _ = json_str!(r#"{"a": [1, 2 3]}"#);
+    let _ = json_str!("{\"a\": tru}");
+    let _ = json_str!(r#"[1]
+        x"#);
+}
@@ -0,0 +1,390 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    error::JsonError,
+    number::Number,
+    tokenizer::{Token, Tokenizer},
+    value::{pointer_index, pointer_tokens, JsonValue},
+    JsonResult,
+};
+
+// A run of `Arena::strings`, `items` or `members`
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    start: u32,
+    len: u32,
+}
+
+impl Span {
+    fn range(self) -> std::ops::Range<usize> {
+        self.start as usize..(self.start + self.len) as usize
+    }
+}
+
+// 12 bytes, numbers live in their own table since a `Number` would double that
+#[derive(Debug, Clone, Copy)]
+enum Node {
+    Null,
+    Boolen(bool),
+    Number(u32),
+    String(Span),
+    Array(Span),
+    Object(Span),
+}
+
+// A whole document in a handful of flat tables: every string value in one buffer,
+// every key once however often it appears, and containers as runs of child indexes.
+// Objects are sorted by key with the last of duplicate keys kept, as in `JsonValue`.
+// Read it through `root()`.
+#[derive(Debug)]
+pub struct Arena {
+    nodes: Vec<Node>,
+    numbers: Vec<Number>,
+    strings: String,
+    keys: Vec<Box<str>>,
+    // array items, node indexes
+    items: Vec<u32>,
+    // object members, (key index, node index)
+    members: Vec<(u32, u32)>,
+    root: u32,
+}
+
+// `parse` into an `Arena`
+pub fn parse_into_arena(json: &str) -> JsonResult<Arena> {
+    let mut builder = Builder {
+        tokenizer: Tokenizer::new(json),
+        arena: Arena {
+            nodes: Vec::new(),
+            numbers: Vec::new(),
+            strings: String::new(),
+            keys: Vec::new(),
+            items: Vec::new(),
+            members: Vec::new(),
+            root: 0,
+        },
+        interned: HashMap::new(),
+        item_stack: Vec::new(),
+        member_stack: Vec::new(),
+    };
+    let token = builder.tokenizer.next()?;
+    builder.arena.root = builder.value(token)?;
+
+    let mut arena = builder.arena;
+    arena.nodes.shrink_to_fit();
+    arena.numbers.shrink_to_fit();
+    arena.strings.shrink_to_fit();
+    arena.keys.shrink_to_fit();
+    arena.items.shrink_to_fit();
+    arena.members.shrink_to_fit();
+    Ok(arena)
+}
+
+struct Builder<'a> {
+    tokenizer: Tokenizer<'a>,
+    arena: Arena,
+    interned: HashMap<String, u32>,
+    // children of the containers being parsed, moved to the arena in one run when
+    // their container closes
+    item_stack: Vec<u32>,
+    member_stack: Vec<(u32, u32)>,
+}
+
+impl Builder<'_> {
+    fn push(&mut self, node: Node) -> JsonResult<u32> {
+        let index = index(self.arena.nodes.len())?;
+        self.arena.nodes.push(node);
+        Ok(index)
+    }
+
+    fn value(&mut self, token: Token) -> JsonResult<u32> {
+        let node = match token {
+            Token::Null => Node::Null,
+            Token::Boolen(b) => Node::Boolen(b),
+            Token::Number(n) => {
+                let index = index(self.arena.numbers.len())?;
+                self.arena.numbers.push(n);
+                Node::Number(index)
+            }
+            Token::String(s) => {
+                let start = index(self.arena.strings.len())?;
+                self.arena.strings.push_str(&s);
+                let len = index(self.arena.strings.len())? - start;
+                Node::String(Span { start, len })
+            }
+            Token::BracketOn => self.array()?,
+            Token::BraceOn => self.object()?,
+            token => return Err(JsonError::unexpected_token(token, self.tokenizer.token_start())),
+        };
+        self.push(node)
+    }
+
+    fn intern(&mut self, key: &str) -> JsonResult<u32> {
+        if let Some(&id) = self.interned.get(key) {
+            return Ok(id);
+        }
+        let id = index(self.arena.keys.len())?;
+        self.arena.keys.push(key.into());
+        self.interned.insert(key.to_string(), id);
+        Ok(id)
+    }
+
+    fn array(&mut self) -> JsonResult<Node> {
+        let base = self.item_stack.len();
+        let mut token = self.tokenizer.next()?;
+        if token != Token::BracketOff {
+            loop {
+                let item = self.value(token)?;
+                self.item_stack.push(item);
+                token = match self.tokenizer.next()? {
+                    Token::Comma => self.tokenizer.next()?,
+                    Token::BracketOff => break,
+                    token => return Err(JsonError::unexpected_token(token, self.tokenizer.token_start())),
+                };
+            }
+        }
+
+        let start = index(self.arena.items.len())?;
+        self.arena.items.extend(self.item_stack.drain(base..));
+        let len = index(self.arena.items.len())? - start;
+        Ok(Node::Array(Span { start, len }))
+    }
+
+    fn object(&mut self) -> JsonResult<Node> {
+        let base = self.member_stack.len();
+        let mut token = self.tokenizer.next()?;
+        if token != Token::BraceOff {
+            loop {
+                let key = match token {
+                    Token::String(key) => self.intern(&key)?,
+                    token => return Err(JsonError::unexpected_token(token, self.tokenizer.token_start())),
+                };
+                match self.tokenizer.next()? {
+                    Token::Colon => (),
+                    token => return Err(JsonError::unexpected_token(token, self.tokenizer.token_start())),
+                }
+                let next = self.tokenizer.next()?;
+                let value = self.value(next)?;
+                self.member_stack.push((key, value));
+                token = match self.tokenizer.next()? {
+                    Token::Comma => self.tokenizer.next()?,
+                    Token::BraceOff => break,
+                    token => return Err(JsonError::unexpected_token(token, self.tokenizer.token_start())),
+                };
+            }
+        }
+
+        // sort by key, stable so the last duplicate is last, then keep only that one
+        let keys = &self.arena.keys;
+        let members = &mut self.member_stack[base..];
+        members.sort_by(|a, b| keys[a.0 as usize].cmp(&keys[b.0 as usize]));
+        let start = index(self.arena.members.len())?;
+        let mut rest = self.member_stack.drain(base..).peekable();
+        while let Some(member) = rest.next() {
+            if rest.peek().is_none_or(|next| next.0 != member.0) {
+                self.arena.members.push(member);
+            }
+        }
+        let len = index(self.arena.members.len())? - start;
+        Ok(Node::Object(Span { start, len }))
+    }
+}
+
+// Indexes and lengths are `u32`, so every table of an arena holds at most `u32::MAX`
+// entries and the string buffer at most `u32::MAX` bytes.
+fn index(len: usize) -> JsonResult<u32> {
+    u32::try_from(len).map_err(|_| {
+        JsonError::TooLarge(format!("an arena table reached {} entries", len))
+    })
+}
+
+impl Arena {
+    pub fn root(&self) -> ArenaValue<'_> {
+        self.value(self.root)
+    }
+
+    fn value(&self, index: u32) -> ArenaValue<'_> {
+        ArenaValue {
+            arena: self,
+            node: self.nodes[index as usize],
+        }
+    }
+}
+
+// A node of an `Arena`, read-only and `Copy`, with the accessors of `JsonValue`
+#[derive(Clone, Copy)]
+pub struct ArenaValue<'a> {
+    arena: &'a Arena,
+    node: Node,
+}
+
+impl<'a> ArenaValue<'a> {
+    pub fn type_name(&self) -> &'static str {
+        match self.node {
+            Node::Null => "null",
+            Node::Boolen(_) => "boolean",
+            Node::String(_) => "string",
+            Node::Number(_) => "number",
+            Node::Array(_) => "array",
+            Node::Object(_) => "object",
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.node, Node::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.node {
+            Node::Boolen(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<Number> {
+        match self.node {
+            Node::Number(i) => Some(self.arena.numbers[i as usize]),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self.node {
+            Node::String(span) => Some(&self.arena.strings[span.range()]),
+            _ => None,
+        }
+    }
+
+    // items of an array or members of an object, 0 for anything else
+    pub fn len(&self) -> usize {
+        match self.node {
+            Node::Array(span) | Node::Object(span) => span.len as usize,
+            _ => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, key: &str) -> Option<ArenaValue<'a>> {
+        let Node::Object(span) = self.node else {
+            return None;
+        };
+        let members = &self.arena.members[span.range()];
+        let keys = &self.arena.keys;
+        let i = members.binary_search_by(|m| (*keys[m.0 as usize]).cmp(key)).ok()?;
+        Some(self.arena.value(members[i].1))
+    }
+
+    pub fn at(&self, index: usize) -> Option<ArenaValue<'a>> {
+        let Node::Array(span) = self.node else {
+            return None;
+        };
+        let item = self.arena.items[span.range()].get(index)?;
+        Some(self.arena.value(*item))
+    }
+
+    // array items, nothing for anything else
+    pub fn items(&self) -> impl Iterator<Item = ArenaValue<'a>> + 'a {
+        let arena = self.arena;
+        let items = match self.node {
+            Node::Array(span) => &arena.items[span.range()],
+            _ => &[],
+        };
+        items.iter().map(move |&i| arena.value(i))
+    }
+
+    // object members in key order, nothing for anything else
+    pub fn entries(&self) -> impl Iterator<Item = (&'a str, ArenaValue<'a>)> + 'a {
+        let arena = self.arena;
+        let members = match self.node {
+            Node::Object(span) => &arena.members[span.range()],
+            _ => &[],
+        };
+        members.iter().map(move |&(key, value)| (&*arena.keys[key as usize], arena.value(value)))
+    }
+
+    // RFC 6901, see `JsonValue::pointer`
+    pub fn pointer(&self, pointer: &str) -> Option<ArenaValue<'a>> {
+        pointer_tokens(pointer)?.try_fold(*self, |value, token| match value.node {
+            Node::Object(_) => value.get(&token),
+            Node::Array(_) => value.at(pointer_index(&token)?),
+            _ => None,
+        })
+    }
+
+    pub fn to_value(&self) -> JsonValue {
+        match self.node {
+            Node::Null => JsonValue::Null,
+            Node::Boolen(b) => JsonValue::Boolen(b),
+            Node::Number(_) => JsonValue::Number(self.as_number().unwrap()),
+            Node::String(_) => JsonValue::String(self.as_str().unwrap().to_string()),
+            Node::Array(_) => JsonValue::Array(self.items().map(|item| item.to_value()).collect()),
+            Node::Object(_) => JsonValue::Object(
+                self.entries()
+                    .map(|(key, value)| (key.to_string(), value.to_value()))
+                    .collect::<BTreeMap<_, _>>(),
+            ),
+        }
+    }
+
+    pub fn dump(&self) -> String {
+        self.to_value().dump()
+    }
+}
+
+impl std::fmt::Debug for ArenaValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_value().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn same_as_parse() {
+        let inputs = [
+            r#"{"b": [1, 2.5, "x", null, true, {}], "a": {"k": "v", "k": "w"}, "é\n": []}"#,
+            r#"[{"id": 1, "tags": ["a"]}, {"id": 2, "tags": []}, {"tags": ["b", "c"], "id": 3}]"#,
+            "\"top\"",
+            "170141183460469231731687303715884105727",
+        ];
+        for input in inputs {
+            let arena = parse_into_arena(input).unwrap();
+            assert_eq!(arena.root().to_value(), parse(input).unwrap(), "{}", input);
+        }
+        assert_eq!(parse_into_arena("[1,").unwrap_err(), JsonError::UnexpectedEndOfJson);
+        assert!(parse_into_arena(r#"{"a" 1}"#).is_err());
+    }
+
+    #[test]
+    fn table_limit() {
+        // a real document this size needs gigabytes, so check the conversion alone
+        assert_eq!(index(u32::MAX as usize).unwrap(), u32::MAX);
+        let err = index(u32::MAX as usize + 1).unwrap_err();
+        assert_eq!(err.to_string(), "too large: an arena table reached 4294967296 entries");
+        assert!(err.is_data());
+    }
+
+    #[test]
+    fn navigation() {
+        let json = r#"[{"id": 1, "name": "ada"}, {"name": "bob", "id": 2}]"#;
+        let arena = parse_into_arena(json).unwrap();
+        let root = arena.root();
+        assert_eq!(arena.keys.len(), 2);
+        assert_eq!((root.type_name(), root.len()), ("array", 2));
+        assert_eq!(root.at(1).unwrap().get("name").unwrap().as_str(), Some("bob"));
+        assert_eq!(root.pointer("/0/id").unwrap().as_number(), Some(Number::Integer(1)));
+        assert!(root.pointer("/2").is_none() && root.get("id").is_none());
+        assert!(root.pointer("/+0/id").is_none());
+
+        let names: Vec<_> = root.items().filter_map(|u| u.get("name")?.as_str()).collect();
+        assert_eq!(names, ["ada", "bob"]);
+        let keys: Vec<_> = root.at(1).unwrap().entries().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["id", "name"]);
+        assert_eq!(root.at(0).unwrap().dump(), r#"{"id":1,"name":"ada"}"#);
+    }
+}
@@ -0,0 +1,263 @@
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    process::ExitCode,
+};
+
+use json::{
+    diff, parse_complete, parse_recovering, Change, JsonError, JsonValue, Position, Severity,
+};
+
+const USAGE: &str = "usage: json <command> [options] [file]
+
+commands:
+    fmt [--indent N] [file]       pretty-print, N spaces per level (default 4)
+    minify [file]                 print without whitespace
+    validate [file]               report every problem with its line and column
+    get <pointer> [file]          print the value at an RFC 6901 pointer, e.g. /users/0/name
+    keys [file]                   print the keys of the top-level object, one per line
+    sort-keys [--indent N] [file] pretty-print with object keys in sorted order, which fmt
+                                  does too since objects are kept sorted
+    diff <a.json> <b.json>        print the paths that differ between two documents
+
+Without a file, or with `-`, the input is read from stdin.
+
+exit status: 0 on success, 1 for invalid json, a missing pointer or differing
+documents, 2 for usage and i/o errors";
+
+enum Failure {
+    // invalid input or a missing value
+    Failed(String),
+    // `diff` found changes, they go to stdout
+    Differ(String),
+    Usage(String),
+    Io(String),
+}
+
+type CliResult<T> = Result<T, Failure>;
+
+struct Args {
+    command: String,
+    indent: u8,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> CliResult<Args> {
+        let mut args = args.into_iter();
+        let command = args.next().ok_or_else(|| Failure::Usage("missing command".to_string()))?;
+        let mut indent = 4;
+        let mut positional = Vec::new();
+
+        while let Some(arg) = args.next() {
+            if let Some(value) = arg.strip_prefix("--indent=") {
+                indent = parse_indent(value)?;
+            } else if arg == "--indent" {
+                let value = args.next().ok_or_else(|| Failure::Usage("--indent needs a value".to_string()))?;
+                indent = parse_indent(&value)?;
+            } else if arg.starts_with("--") {
+                return Err(Failure::Usage(format!("unknown option `{}`", arg)));
+            } else {
+                positional.push(arg);
+            }
+        }
+
+        Ok(Args {
+            command,
+            indent,
+            positional,
+        })
+    }
+
+    // the positional arguments after the `required` ones, an input file at most
+    fn input(&self, required: usize) -> CliResult<Option<&str>> {
+        match &self.positional[..] {
+            args if args.len() < required => Err(Failure::Usage(format!("`{}` needs more arguments", self.command))),
+            args if args.len() > required + 1 => Err(Failure::Usage(format!("`{}` takes a single input", self.command))),
+            args => Ok(args.get(required).map(String::as_str)),
+        }
+    }
+}
+
+fn parse_indent(value: &str) -> CliResult<u8> {
+    value
+        .parse()
+        .map_err(|_| Failure::Usage(format!("invalid indent `{}`", value)))
+}
+
+fn read(file: Option<&str>) -> CliResult<(String, String)> {
+    match file {
+        None | Some("-") => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|e| Failure::Io(format!("<stdin>: {}", e)))?;
+            Ok(("<stdin>".to_string(), text))
+        }
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|e| Failure::Io(format!("{}: {}", path, e)))?;
+            Ok((path.to_string(), text))
+        }
+    }
+}
+
+fn load(file: Option<&str>) -> CliResult<JsonValue> {
+    let (name, text) = read(file)?;
+    check(&name, &text)
+}
+
+// `parse_complete`, so content after the document is an error too, reported at its
+// line and column
+fn check(name: &str, text: &str) -> CliResult<JsonValue> {
+    parse_complete(text).map_err(|error| {
+        let offset = match error {
+            JsonError::UnexpectedEndOfJson => Some(text.len()),
+            ref error => error.offset(),
+        };
+        match offset {
+            Some(offset) => {
+                let at = Position::locate(text, offset);
+                Failure::Failed(format!("{}:{}:{}: {}", name, at.line, at.column, error))
+            }
+            None => Failure::Failed(format!("{}: {}", name, error)),
+        }
+    })
+}
+
+fn validate(file: Option<&str>) -> CliResult<String> {
+    let (name, text) = read(file)?;
+    let (_, diagnostics) = parse_recovering(&text);
+    let mut report = String::new();
+    for d in &diagnostics {
+        let severity = match d.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        report.push_str(&format!(
+            "{}:{}:{}: {}: {}\n",
+            name, d.position.line, d.position.column, severity, d.error
+        ));
+    }
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        return Err(Failure::Failed(report.trim_end().to_string()));
+    }
+    Ok(report)
+}
+
+fn keys(value: &JsonValue) -> CliResult<String> {
+    match value {
+        JsonValue::Object(object) => Ok(object.keys().map(|k| format!("{}\n", k)).collect()),
+        other => Err(Failure::Failed(format!("expected an object, found {}", other.type_name()))),
+    }
+}
+
+// one line per difference, keyed by the pointer of the changed value
+fn lines(changes: &[Change]) -> String {
+    changes.iter().map(|c| c.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+fn run(args: &Args) -> CliResult<String> {
+    let line = |s: String| s + "\n";
+    match args.command.as_str() {
+        "fmt" | "sort-keys" => Ok(line(load(args.input(0)?)?.pretty(args.indent))),
+        "minify" => Ok(line(load(args.input(0)?)?.dump())),
+        "validate" => validate(args.input(0)?),
+        "get" => {
+            let value = load(args.input(1)?)?;
+            let pointer = &args.positional[0];
+            match value.pointer(pointer) {
+                Some(found) => Ok(line(found.pretty(args.indent))),
+                None => Err(Failure::Failed(format!("no value at `{}`", pointer))),
+            }
+        }
+        "keys" => keys(&load(args.input(0)?)?),
+        "diff" => {
+            if args.positional.len() != 2 {
+                return Err(Failure::Usage("`diff` takes two files".to_string()));
+            }
+            let a = load(Some(&args.positional[0]))?;
+            let b = load(Some(&args.positional[1]))?;
+            let changes = diff(&a, &b);
+            match changes.is_empty() {
+                true => Ok(String::new()),
+                false => Err(Failure::Differ(lines(&changes))),
+            }
+        }
+        "help" | "-h" | "--help" => Ok(line(USAGE.to_string())),
+        command => Err(Failure::Usage(format!("unknown command `{}`", command))),
+    }
+}
+
+fn main() -> ExitCode {
+    let result = Args::parse(env::args().skip(1)).and_then(|args| run(&args));
+    match result {
+        Ok(out) => {
+            // a closed pipe (`json fmt big.json | head`) is not an error
+            let _ = io::stdout().lock().write_all(out.as_bytes());
+            ExitCode::SUCCESS
+        }
+        Err(Failure::Failed(msg)) => {
+            eprintln!("{}", msg);
+            ExitCode::from(1)
+        }
+        Err(Failure::Differ(changes)) => {
+            let _ = writeln!(io::stdout().lock(), "{}", changes);
+            ExitCode::from(1)
+        }
+        Err(Failure::Usage(msg)) => {
+            eprintln!("json: {}\n\n{}", msg, USAGE);
+            ExitCode::from(2)
+        }
+        Err(Failure::Io(msg)) => {
+            eprintln!("json: {}", msg);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use json::{array, object};
+
+    fn args(s: &str) -> CliResult<Args> {
+        Args::parse(s.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parse_args() {
+        let a = args("fmt --indent 2 a.json").ok().unwrap();
+        assert_eq!((a.command.as_str(), a.indent, a.positional.len()), ("fmt", 2, 1));
+        assert_eq!(args("fmt --indent=8").ok().unwrap().indent, 8);
+        assert!(matches!(args("fmt --indent x"), Err(Failure::Usage(_))));
+        assert!(matches!(args("fmt --tabs"), Err(Failure::Usage(_))));
+        assert!(matches!(args("fmt a b").ok().unwrap().input(0), Err(Failure::Usage(_))));
+    }
+
+    #[test]
+    fn diff_paths() {
+        let a = object! {"a" => 1, "b" => array![1, 2], "c/d" => true};
+        let b = object! {"a" => 2, "b" => array![1], "e" => JsonValue::Null, "c/d" => true};
+        assert_eq!(lines(&diff(&a, &b)), "~ /a: 1 -> 2\n- /b/1: 2\n+ /e: null");
+    }
+
+    #[test]
+    fn load_errors() {
+        let value = check("a.json", r#"{"b": [1], "a": null}"#).ok().unwrap();
+        assert_eq!(value.pretty(2), "{\n  \"a\": null,\n  \"b\": [\n    1\n  ]\n}");
+
+        let error = |text| match check("a.json", text) {
+            Err(Failure::Failed(msg)) => msg,
+            _ => panic!("{} loaded", text),
+        };
+        assert_eq!(error("{\n  \"a\": 1 2}"), "a.json:2:10: unexpected token `2` at byte 11");
+        assert_eq!(error("[1,\n"), "a.json:2:1: unexpected end of json");
+        assert_eq!(error("[1] x"), "a.json:1:5: unexpected character 'x' at byte 4");
+    }
+
+    #[test]
+    fn keys_of_object() {
+        assert_eq!(keys(&object! {"b" => 1, "a" => 2}).ok().unwrap(), "a\nb\n");
+        assert!(keys(&array![]).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use crate::{
+    number::Number,
+    parser::{Build, Parser},
+    tokenizer::{Token, Tokenizer},
+    value::JsonValue,
+    JsonResult,
+};
+
+// Same shape as `JsonValue`, but strings and keys borrow from the input
+// whenever they contain no escape sequence.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValueRef<'a> {
+    Null,
+    Boolen(bool),
+    String(Cow<'a, str>),
+    Number(Number),
+    Array(Vec<JsonValueRef<'a>>),
+    Object(BTreeMap<Cow<'a, str>, JsonValueRef<'a>>),
+}
+
+impl<'a> JsonValueRef<'a> {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValueRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValueRef<'a>> {
+        match self {
+            JsonValueRef::Object(object) => object.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn into_owned(self) -> JsonValue {
+        match self {
+            JsonValueRef::Null => JsonValue::Null,
+            JsonValueRef::Boolen(b) => JsonValue::Boolen(b),
+            JsonValueRef::String(s) => JsonValue::String(s.into_owned()),
+            JsonValueRef::Number(n) => JsonValue::Number(n),
+            JsonValueRef::Array(array) => {
+                JsonValue::Array(array.into_iter().map(JsonValueRef::into_owned).collect())
+            }
+            JsonValueRef::Object(object) => JsonValue::Object(
+                object
+                    .into_iter()
+                    .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl<'a> From<JsonValueRef<'a>> for JsonValue {
+    fn from(value: JsonValueRef<'a>) -> Self {
+        value.into_owned()
+    }
+}
+
+impl<'a> Build<'a> for JsonValueRef<'a> {
+    type Key = Cow<'a, str>;
+
+    fn scalar(token: Token<'a>) -> Result<Self, Token<'a>> {
+        Ok(match token {
+            Token::Null => JsonValueRef::Null,
+            Token::Boolen(b) => JsonValueRef::Boolen(b),
+            Token::Number(n) => JsonValueRef::Number(n),
+            Token::String(s) => JsonValueRef::String(s),
+            Token::Wtf8(s) => JsonValueRef::String(Cow::Owned(s.to_string_lossy())),
+            token => return Err(token),
+        })
+    }
+
+    fn key(key: Cow<'a, str>) -> Self::Key {
+        key
+    }
+
+    fn array(items: Vec<Self>) -> Self {
+        JsonValueRef::Array(items)
+    }
+
+    fn object(members: BTreeMap<Self::Key, Self>) -> Self {
+        JsonValueRef::Object(members)
+    }
+}
+
+pub fn parse_borrowed(json: &str) -> JsonResult<JsonValueRef<'_>> {
+    Parser::with_tokenizer(json, Tokenizer::new(json)).value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, error::JsonError, object, parse};
+
+    #[test]
+    fn borrows_unescaped_strings() {
+        let s = r#"{"name": "abc", "escaped\n": "a\"b", "list": ["x", 1, null]}"#;
+        let value = parse_borrowed(s).unwrap();
+
+        match value.get("name") {
+            Some(JsonValueRef::String(Cow::Borrowed(name))) => assert_eq!(*name, "abc"),
+            other => panic!("expected a borrowed string, found {:?}", other),
+        }
+        match value.get("escaped\n") {
+            Some(JsonValueRef::String(Cow::Owned(name))) => assert_eq!(name, "a\"b"),
+            other => panic!("expected an owned string, found {:?}", other),
+        }
+        if let JsonValueRef::Object(object) = &value {
+            assert!(object.keys().any(|k| matches!(k, Cow::Owned(_))));
+        }
+    }
+
+    #[test]
+    fn into_owned() {
+        let s = r#"{"a": [1, 2.5, true], "b": {"c": "é"}}"#;
+        let value = parse_borrowed(s).unwrap().into_owned();
+        assert_eq!(value, parse(s).unwrap());
+        assert_eq!(
+            value,
+            object! {"a" => array![1, 2.5, true], "b" => object! {"c" => "é"}}
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_borrowed("[1,"), Err(JsonError::UnexpectedEndOfJson));
+        assert!(parse_borrowed(r#"{"a" 1}"#).is_err());
+    }
+}
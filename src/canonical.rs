@@ -0,0 +1,193 @@
+use crate::{
+    error::JsonError, generator::escape_into, number::Number, parser::parse_complete,
+    value::JsonValue, JsonResult,
+};
+
+// RFC 8785 JSON Canonicalization Scheme: no whitespace, keys sorted by their utf-16
+// code units, numbers as ECMAScript prints doubles and only the escapes json requires.
+// NaN, infinities and strings with lone surrogates have no canonical form.
+pub fn to_canonical_string(value: &JsonValue) -> JsonResult<String> {
+    let mut code = String::new();
+    write_canonical(&mut code, value)?;
+    Ok(code)
+}
+
+fn write_canonical(code: &mut String, value: &JsonValue) -> JsonResult<()> {
+    match value {
+        JsonValue::Null => code.push_str("null"),
+        JsonValue::Boolen(b) => code.push_str(if *b { "true" } else { "false" }),
+        JsonValue::Number(n) => code.push_str(&es_number(n)?),
+        JsonValue::String(s) => write_string(code, s),
+        JsonValue::Wtf8(_) => {
+            return Err(JsonError::invalid_type(
+                "expected string, found string with lone surrogates".to_string(),
+            ))
+        }
+        JsonValue::Array(array) => {
+            code.push('[');
+            for (i, item) in array.iter().enumerate() {
+                if i > 0 {
+                    code.push(',');
+                }
+                write_canonical(code, item)?;
+            }
+            code.push(']');
+        }
+        // canonical form is about the content, not the text it was written as
+        JsonValue::Raw(raw) => write_canonical(code, &parse_complete(raw)?)?,
+        JsonValue::Object(object) => {
+            let mut members: Vec<_> = object.iter().collect();
+            // BTreeMap order is utf-8 byte order, which differs above U+FFFF
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            code.push('{');
+            for (i, (key, value)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    code.push(',');
+                }
+                write_string(code, key);
+                code.push(':');
+                write_canonical(code, value)?;
+            }
+            code.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_string(code: &mut String, s: &str) {
+    code.push('"');
+    escape_into(code, s);
+    code.push('"');
+}
+
+// Number.prototype.toString, integers are doubles too and may lose precision
+fn es_number(n: &Number) -> JsonResult<String> {
+    let v = n.as_f64();
+    if !v.is_finite() {
+        return Err(JsonError::InvalidType(format!("{} has no json form", v)));
+    }
+    if v == 0.0 {
+        return Ok("0".to_string());
+    }
+
+    // the fewest digits that round-trip, "1.2345e-7" -> digits "12345", n = -6. `{:e}`
+    // finds how many, formatting again at that precision picks the closest such
+    // number and breaks ties to even as ECMAScript does (1424953923781206.25 -> .2)
+    let shortest = format!("{:e}", v.abs());
+    let precision = shortest.split_once('e').unwrap().0.len().saturating_sub(2);
+    let sci = format!("{:.*e}", precision, v.abs());
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    let n = exp.parse::<i32>().unwrap() + 1;
+
+    let mut s = String::new();
+    if v < 0.0 {
+        s.push('-');
+    }
+    if k <= n && n <= 21 {
+        s.push_str(&digits);
+        s.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        s.push_str(&digits[..n as usize]);
+        s.push('.');
+        s.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        s.push_str("0.");
+        s.extend(std::iter::repeat_n('0', -n as usize));
+        s.push_str(&digits);
+    } else {
+        s.push_str(&digits[..1]);
+        if k > 1 {
+            s.push('.');
+            s.push_str(&digits[1..]);
+        }
+        s.push('e');
+        s.push(if n - 1 < 0 { '-' } else { '+' });
+        s.push_str(&(n - 1).abs().to_string());
+    }
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    // RFC 8785 section 3.2.2
+    #[test]
+    fn rfc_example() {
+        let input = r#"{
+  "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+  "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+  "literals": [null, true, false]
+}"#;
+        assert_eq!(
+            to_canonical_string(&parse(input).unwrap()).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    // RFC 8785 section 3.2.3
+    #[test]
+    fn utf16_key_order() {
+        let input = r#"{
+  "€": "Euro Sign",
+  "\r": "Carriage Return",
+  "\ufb33": "Hebrew Letter Dalet With Dagesh",
+  "1": "One",
+  "😀": "Emoji: Grinning Face",
+  "\u0080": "Control",
+  "ö": "Latin Small Letter O With Diaeresis"
+}"#;
+        assert_eq!(
+            to_canonical_string(&parse(input).unwrap()).unwrap(),
+            concat!(
+                r#"{"\r":"Carriage Return","1":"One","#,
+                "\"\u{80}\":\"Control\",",
+                r#""ö":"Latin Small Letter O With Diaeresis","€":"Euro Sign","#,
+                "\"😀\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}"
+            )
+        );
+    }
+
+    // RFC 8785 appendix B
+    #[test]
+    fn number_vectors() {
+        let vectors: &[(u64, &str)] = &[
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+        for &(bits, expected) in vectors {
+            let n = Number::Float(f64::from_bits(bits));
+            assert_eq!(es_number(&n).unwrap(), expected, "{:#x}", bits);
+        }
+        for bits in [0x7fffffffffffffff, 0x7ff0000000000000] {
+            let err = es_number(&Number::Float(f64::from_bits(bits))).unwrap_err();
+            assert!(err.is_data(), "{}", err);
+        }
+        assert_eq!(es_number(&Number::Integer(9007199254740993)).unwrap(), "9007199254740992");
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{error::JsonError, number::Number, value::JsonValue, wtf8::Wtf8String, JsonResult};
+
+// struct <-> JsonValue mapping, usually implemented with `#[derive(ToJson, FromJson)]`
+// from the companion `json_derive` crate.
+pub trait ToJson {
+    fn to_json(&self) -> JsonValue;
+}
+
+pub trait FromJson: Sized {
+    fn from_json(value: &JsonValue) -> JsonResult<Self>;
+
+    // called when an object field is absent, `Option` turns it into `None`
+    fn missing_field(field: &str) -> JsonResult<Self> {
+        Err(JsonError::undefined_field(field.to_string()))
+    }
+}
+
+impl ToJson for JsonValue {
+    fn to_json(&self) -> JsonValue {
+        self.clone()
+    }
+}
+
+impl FromJson for JsonValue {
+    fn from_json(value: &JsonValue) -> JsonResult<Self> {
+        Ok(value.clone())
+    }
+}
+
+impl ToJson for Number {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Number(*self)
+    }
+}
+
+impl FromJson for Number {
+    fn from_json(value: &JsonValue) -> JsonResult<Self> {
+        match value {
+            JsonValue::Number(n) => Ok(*n),
+            other => Err(JsonError::expected("number", other)),
+        }
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Boolen(*self)
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(value: &JsonValue) -> JsonResult<Self> {
+        match value {
+            JsonValue::Boolen(b) => Ok(*b),
+            other => Err(JsonError::expected("boolean", other)),
+        }
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_string())
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.clone())
+    }
+}
+
+impl FromJson for String {
+    fn from_json(value: &JsonValue) -> JsonResult<Self> {
+        match value {
+            JsonValue::String(s) => Ok(s.clone()),
+            JsonValue::Wtf8(_) => Err(JsonError::invalid_type(
+                "expected string, found string with lone surrogates".to_string(),
+            )),
+            other => Err(JsonError::expected("string", other)),
+        }
+    }
+}
+
+impl ToJson for Wtf8String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Wtf8(self.clone())
+    }
+}
+
+impl FromJson for Wtf8String {
+    fn from_json(value: &JsonValue) -> JsonResult<Self> {
+        match value {
+            JsonValue::String(s) => Ok(s.as_str().into()),
+            JsonValue::Wtf8(s) => Ok(s.clone()),
+            other => Err(JsonError::expected("string", other)),
+        }
+    }
+}
+
+impl ToJson for char {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_string())
+    }
+}
+
+impl FromJson for char {
+    fn from_json(value: &JsonValue) -> JsonResult<Self> {
+        if let JsonValue::String(s) = value {
+            let mut chars = s.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                return Ok(c);
+            }
+        }
+        Err(JsonError::expected("a single character string", value))
+    }
+}
+
+macro_rules! impl_int_conversions {
+    ($($t: ident), *) => {
+      $(
+        impl ToJson for $t {
+            fn to_json(&self) -> JsonValue {
+                JsonValue::Number((*self).into())
+            }
+        }
+
+        impl FromJson for $t {
+            fn from_json(value: &JsonValue) -> JsonResult<Self> {
+                match value {
+                    JsonValue::Number(n) if n.is_integer() => $t::try_from(n.as_i128().unwrap())
+                        .map_err(|_| JsonError::invalid_type(format!("expected {}, found {}", stringify!($t), n))),
+                    other => Err(JsonError::expected(stringify!($t), other)),
+                }
+            }
+        }
+      )*
+    };
+}
+
+macro_rules! impl_float_conversions {
+    ($($t: ident), *) => {
+      $(
+        impl ToJson for $t {
+            fn to_json(&self) -> JsonValue {
+                JsonValue::Number((*self).into())
+            }
+        }
+
+        impl FromJson for $t {
+            fn from_json(value: &JsonValue) -> JsonResult<Self> {
+                match value {
+                    JsonValue::Number(n) => Ok(n.as_f64() as $t),
+                    other => Err(JsonError::expected(stringify!($t), other)),
+                }
+            }
+        }
+      )*
+    };
+}
+
+impl_int_conversions!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_float_conversions!(f32, f64);
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> JsonValue {
+        match self {
+            Some(value) => value.to_json(),
+            None => JsonValue::Null,
+        }
+    }
+}
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &JsonValue) -> JsonResult<Self> {
+        match value {
+            JsonValue::Null => Ok(None),
+            value => T::from_json(value).map(Some),
+        }
+    }
+
+    fn missing_field(_field: &str) -> JsonResult<Self> {
+        Ok(None)
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Box<T> {
+    fn to_json(&self) -> JsonValue {
+        (**self).to_json()
+    }
+}
+
+impl<T: FromJson> FromJson for Box<T> {
+    fn from_json(value: &JsonValue) -> JsonResult<Self> {
+        T::from_json(value).map(Box::new)
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> JsonValue {
+        (**self).to_json()
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_slice().to_json()
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &JsonValue) -> JsonResult<Self> {
+        match value {
+            JsonValue::Array(array) => array
+                .iter()
+                .enumerate()
+                .map(|(i, item)| T::from_json(item).map_err(|e| e.at_index(i)))
+                .collect(),
+            other => Err(JsonError::expected("array", other)),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for BTreeMap<String, T> {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(self.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+    }
+}
+
+impl<T: FromJson> FromJson for BTreeMap<String, T> {
+    fn from_json(value: &JsonValue) -> JsonResult<Self> {
+        match value {
+            JsonValue::Object(object) => object
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), T::from_json(v).map_err(|e| e.at_field(k))?)))
+                .collect(),
+            other => Err(JsonError::expected("object", other)),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for HashMap<String, T> {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(self.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+    }
+}
+
+impl<T: FromJson> FromJson for HashMap<String, T> {
+    fn from_json(value: &JsonValue) -> JsonResult<Self> {
+        match value {
+            JsonValue::Object(object) => object
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), T::from_json(v).map_err(|e| e.at_field(k))?)))
+                .collect(),
+            other => Err(JsonError::expected("object", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, object};
+
+    #[test]
+    fn primitives() {
+        assert_eq!(u8::from_json(&200.into()), Ok(200));
+        assert_eq!(
+            u8::from_json(&300.into()),
+            Err(JsonError::InvalidType("expected u8, found 300".to_string()))
+        );
+        assert_eq!(f64::from_json(&3.into()), Ok(3.0));
+        assert_eq!(char::from_json(&"x".into()), Ok('x'));
+        assert_eq!(Option::<bool>::from_json(&JsonValue::Null), Ok(None));
+        assert_eq!(Option::<bool>::missing_field("a"), Ok(None));
+        assert_eq!(
+            bool::missing_field("a"),
+            Err(JsonError::UndefinedField("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn collections_carry_paths() {
+        let value = object! {"a" => array![1, 2, "x"]};
+        assert_eq!(
+            BTreeMap::<String, Vec<i32>>::from_json(&value),
+            Err(JsonError::InvalidType("a[2]: expected i32, found string".to_string()))
+        );
+        let map: HashMap<String, Vec<i32>> = [("a".to_string(), vec![1])].into_iter().collect();
+        assert_eq!(map.to_json(), object! {"a" => array![1]});
+    }
+}
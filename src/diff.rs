@@ -0,0 +1,390 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Display,
+};
+
+use crate::value::{escape_pointer, JsonValue};
+
+// how the items of two arrays are paired up before they are compared
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ArrayMatch {
+    // item i with item i, extra items are added or removed at the end
+    #[default]
+    Index,
+    // keep the longest common subsequence, so an insertion in the middle is one
+    // `Added` instead of a change to every item after it
+    Lcs,
+    // objects with equal values for this key are the same item wherever they are,
+    // items without it are paired only with an equal item. Moves are not reported.
+    Key(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    pub arrays: ArrayMatch,
+}
+
+// One difference, keyed by the RFC 6901 pointer of the value. Pointers into arrays
+// use the index in the old document for `Removed` and in the new one otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added { path: String, value: JsonValue },
+    Removed { path: String, value: JsonValue },
+    // same type, different value
+    Changed { path: String, old: JsonValue, new: JsonValue },
+    TypeChanged { path: String, old: JsonValue, new: JsonValue },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. }
+            | Change::TypeChanged { path, .. } => path,
+        }
+    }
+}
+
+// + /e: null
+// - /b/1: 2
+// ~ /a: 1 -> 2
+// ~ /c: 1 -> "1" (number -> string)
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {}: {}", path, value.dump()),
+            Change::Removed { path, value } => write!(f, "- {}: {}", path, value.dump()),
+            Change::Changed { path, old, new } => {
+                write!(f, "~ {}: {} -> {}", path, old.dump(), new.dump())
+            }
+            Change::TypeChanged { path, old, new } => write!(
+                f,
+                "~ {}: {} -> {} ({} -> {})",
+                path,
+                old.dump(),
+                new.dump(),
+                old.type_name(),
+                new.type_name()
+            ),
+        }
+    }
+}
+
+// the changes that turn `old` into `new`, numbers compare by value (`1` and `1.0` are
+// the same) and object keys in sorted order
+pub fn diff(old: &JsonValue, new: &JsonValue) -> Vec<Change> {
+    diff_with(old, new, &DiffOptions::default())
+}
+
+pub fn diff_with(old: &JsonValue, new: &JsonValue, options: &DiffOptions) -> Vec<Change> {
+    let mut differ = Differ {
+        options,
+        changes: Vec::new(),
+    };
+    differ.value(String::new(), old, new);
+    differ.changes
+}
+
+struct Differ<'a> {
+    options: &'a DiffOptions,
+    changes: Vec<Change>,
+}
+
+impl Differ<'_> {
+    fn value(&mut self, path: String, old: &JsonValue, new: &JsonValue) {
+        match (old, new) {
+            (JsonValue::Object(old), JsonValue::Object(new)) => self.object(&path, old, new),
+            (JsonValue::Array(old), JsonValue::Array(new)) => match &self.options.arrays {
+                ArrayMatch::Index => self.by_index(&path, old, new),
+                ArrayMatch::Lcs => self.by_lcs(&path, old, new),
+                ArrayMatch::Key(key) => self.by_key(&path, old, new, key),
+            },
+            (old, new) if old.semantic_eq(new) => (),
+            (old, new) => {
+                let (old, new) = (old.clone(), new.clone());
+                self.changes.push(match old.type_name() == new.type_name() {
+                    true => Change::Changed { path, old, new },
+                    false => Change::TypeChanged { path, old, new },
+                });
+            }
+        }
+    }
+
+    fn added(&mut self, path: String, value: &JsonValue) {
+        let value = value.clone();
+        self.changes.push(Change::Added { path, value });
+    }
+
+    fn removed(&mut self, path: String, value: &JsonValue) {
+        let value = value.clone();
+        self.changes.push(Change::Removed { path, value });
+    }
+
+    fn object(
+        &mut self,
+        path: &str,
+        old: &BTreeMap<String, JsonValue>,
+        new: &BTreeMap<String, JsonValue>,
+    ) {
+        for (key, value) in old {
+            let path = format!("{}/{}", path, escape_pointer(key));
+            match new.get(key) {
+                Some(other) => self.value(path, value, other),
+                None => self.removed(path, value),
+            }
+        }
+        for (key, value) in new.iter().filter(|(k, _)| !old.contains_key(*k)) {
+            self.added(format!("{}/{}", path, escape_pointer(key)), value);
+        }
+    }
+
+    fn by_index(&mut self, path: &str, old: &[JsonValue], new: &[JsonValue]) {
+        for (i, value) in old.iter().enumerate() {
+            match new.get(i) {
+                Some(other) => self.value(format!("{}/{}", path, i), value, other),
+                None => self.removed(format!("{}/{}", path, i), value),
+            }
+        }
+        for (i, value) in new.iter().enumerate().skip(old.len()) {
+            self.added(format!("{}/{}", path, i), value);
+        }
+    }
+
+    // Between two kept items the old ones were replaced by the new ones: the first
+    // of each are paired and compared in depth, the rest are removed or added.
+    fn by_lcs(&mut self, path: &str, old: &[JsonValue], new: &[JsonValue]) {
+        // lengths[i][j]: lcs of old[i..] and new[j..]
+        let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lengths[i][j] = match old[i].semantic_eq(&new[j]) {
+                    true => lengths[i + 1][j + 1] + 1,
+                    false => lengths[i + 1][j].max(lengths[i][j + 1]),
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        let (mut gap_i, mut gap_j) = (0, 0);
+        loop {
+            let kept = i < old.len() && j < new.len() && old[i].semantic_eq(&new[j]);
+            let end = i == old.len() && j == new.len();
+            if kept || end {
+                self.gap(path, &old[gap_i..i], gap_i, &new[gap_j..j], gap_j);
+                if end {
+                    return;
+                }
+                i += 1;
+                j += 1;
+                (gap_i, gap_j) = (i, j);
+            } else if j == new.len() || (i < old.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+
+    fn gap(
+        &mut self,
+        path: &str,
+        old: &[JsonValue],
+        old_at: usize,
+        new: &[JsonValue],
+        new_at: usize,
+    ) {
+        for (k, (value, other)) in old.iter().zip(new).enumerate() {
+            self.value(format!("{}/{}", path, new_at + k), value, other);
+        }
+        for (k, value) in old.iter().enumerate().skip(new.len()) {
+            self.removed(format!("{}/{}", path, old_at + k), value);
+        }
+        for (k, value) in new.iter().enumerate().skip(old.len()) {
+            self.added(format!("{}/{}", path, new_at + k), value);
+        }
+    }
+
+    fn by_key(&mut self, path: &str, old: &[JsonValue], new: &[JsonValue], key: &str) {
+        // what an item is matched by: the value of `key` in an object that has it, the
+        // whole item otherwise. The flag keeps the two kinds apart.
+        fn id<'v>(value: &'v JsonValue, key: &str) -> (bool, &'v JsonValue) {
+            match value {
+                JsonValue::Object(object) if object.contains_key(key) => (true, &object[key]),
+                value => (false, value),
+            }
+        }
+        // new items by the hash of their id, in order. `content_hash` agrees with
+        // `semantic_eq`, a bucket can still hold unequal ids that collide.
+        let ids: Vec<_> = new.iter().map(|value| id(value, key)).collect();
+        let mut buckets: HashMap<(bool, u64), VecDeque<usize>> = HashMap::new();
+        for (j, &(keyed, id)) in ids.iter().enumerate() {
+            if let Ok(hash) = id.content_hash() {
+                buckets.entry((keyed, hash)).or_default().push_back(j);
+            }
+        }
+
+        // paired[j]: the old item paired with new[j], the first unpaired one with its id
+        let mut paired = vec![None; new.len()];
+        for (i, value) in old.iter().enumerate() {
+            let (keyed, old_id) = id(value, key);
+            let hash = old_id.content_hash().ok();
+            let found = hash.and_then(|hash| buckets.get_mut(&(keyed, hash))).and_then(|bucket| {
+                let at = bucket.iter().position(|&j| ids[j].1.semantic_eq(old_id))?;
+                bucket.remove(at)
+            });
+            match found {
+                Some(j) => paired[j] = Some(i),
+                None => self.removed(format!("{}/{}", path, i), value),
+            }
+        }
+        for (j, value) in new.iter().enumerate() {
+            match paired[j] {
+                Some(i) => self.value(format!("{}/{}", path, j), &old[i], value),
+                None => self.added(format!("{}/{}", path, j), value),
+            }
+        }
+    }
+}
+
+// A unified-diff style report, each change under an `@@ pointer @@` header with the
+// old value on `-` lines and the new one on `+` lines, pretty-printed. With `color`
+// the lines get ANSI red, green and cyan.
+pub fn render_diff(changes: &[Change], color: bool) -> String {
+    let paint = |code: &str, line: String| match color {
+        true => format!("\x1b[{}m{}\x1b[0m\n", code, line),
+        false => line + "\n",
+    };
+    let lines = |prefix: char, value: &JsonValue| {
+        let code = if prefix == '-' { "31" } else { "32" };
+        value
+            .pretty(2)
+            .lines()
+            .map(|line| paint(code, format!("{}{}", prefix, line)))
+            .collect::<String>()
+    };
+
+    let mut out = String::new();
+    for change in changes {
+        let (old, new) = match change {
+            Change::Added { value, .. } => (None, Some(value)),
+            Change::Removed { value, .. } => (Some(value), None),
+            Change::Changed { old, new, .. } | Change::TypeChanged { old, new, .. } => {
+                (Some(old), Some(new))
+            }
+        };
+        let header = match change {
+            Change::TypeChanged { path, old, new } => {
+                format!("@@ {} ({} -> {}) @@", path, old.type_name(), new.type_name())
+            }
+            change => format!("@@ {} @@", change.path()),
+        };
+        out.push_str(&paint("36", header));
+        if let Some(old) = old {
+            out.push_str(&lines('-', old));
+        }
+        if let Some(new) = new {
+            out.push_str(&lines('+', new));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, object};
+
+    fn paths(changes: &[Change]) -> Vec<String> {
+        changes.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn changes() {
+        let old = object! {"a" => 1, "b" => array![1, 2], "c/d" => true, "n" => 1.0, "t" => 1};
+        let new = object! {
+            "a" => 2, "b" => array![1], "c/d" => true, "e" => JsonValue::Null, "n" => 1, "t" => "1"
+        };
+        assert_eq!(
+            paths(&diff(&old, &new)),
+            vec![
+                "~ /a: 1 -> 2",
+                "- /b/1: 2",
+                r#"~ /t: 1 -> "1" (number -> string)"#,
+                "+ /e: null",
+            ]
+        );
+        assert!(diff(&old, &old).is_empty());
+        assert_eq!(diff(&1.into(), &true.into())[0].path(), "");
+    }
+
+    #[test]
+    fn array_strategies() {
+        let old = array![1, 2, 3, 4];
+        let new = array![1, 9, 3, 5, 4];
+        assert_eq!(
+            paths(&diff(&old, &new)),
+            vec!["~ /1: 2 -> 9", "~ /3: 4 -> 5", "+ /4: 4"]
+        );
+
+        let lcs = DiffOptions { arrays: ArrayMatch::Lcs };
+        assert_eq!(
+            paths(&diff_with(&old, &new, &lcs)),
+            vec!["~ /1: 2 -> 9", "+ /3: 5"]
+        );
+        let new = array![0, 1, 2, 3];
+        assert_eq!(
+            paths(&diff_with(&old, &new, &lcs)),
+            vec!["+ /0: 0", "- /3: 4"]
+        );
+
+        let by_id = DiffOptions { arrays: ArrayMatch::Key("id".to_string()) };
+        let old = array![object! {"id" => 1, "v" => "a"}, object! {"id" => 2, "v" => "b"}, "x"];
+        let new = array!["x", object! {"id" => 3}, object! {"id" => 1, "v" => "c"}];
+        assert_eq!(
+            paths(&diff_with(&old, &new, &by_id)),
+            vec![r#"- /1: {"id":2,"v":"b"}"#, r#"+ /1: {"id":3}"#, r#"~ /2/v: "a" -> "c""#]
+        );
+
+        // ids compare as numbers, a repeated id pairs up in order, a keyed object never
+        // pairs with an unkeyed item equal to its id
+        let old = array![object! {"id" => 1, "v" => 1}, object! {"id" => 1, "v" => 2}, 1];
+        let new = array![object! {"id" => 1.0, "v" => 1}, 1, object! {"id" => 1, "v" => 3}];
+        assert_eq!(
+            paths(&diff_with(&old, &new, &by_id)),
+            vec!["~ /2/v: 2 -> 3"]
+        );
+
+        // a thousand records shuffled pair up without changes
+        let record = |i: i32| object! {"id" => i, "n" => i};
+        let old = JsonValue::Array((0..1000).map(record).collect());
+        let new = JsonValue::Array((0..1000).rev().map(record).collect());
+        assert!(diff_with(&old, &new, &by_id).is_empty());
+    }
+
+    #[test]
+    fn render() {
+        let changes = diff(
+            &object! {"a" => array![1], "b" => 1},
+            &object! {"a" => array![], "b" => "x", "c" => 2},
+        );
+        assert_eq!(
+            render_diff(&changes, false),
+            "@@ /a/0 @@\n-1\n@@ /b (number -> string) @@\n-1\n+\"x\"\n@@ /c @@\n+2\n"
+        );
+        assert_eq!(
+            render_diff(&changes[..1], true),
+            "\x1b[36m@@ /a/0 @@\x1b[0m\n\x1b[31m-1\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn assert_json_eq() {
+        crate::assert_json_eq!(array![1, 2.0], array![1.0, 2]);
+        let result = std::panic::catch_unwind(|| {
+            crate::assert_json_eq!(object! {"a" => 1}, object! {"a" => 2});
+        });
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.ends_with("@@ /a @@\n-1\n+2\n"), "{}", message);
+    }
+}
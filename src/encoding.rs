@@ -0,0 +1,189 @@
+use std::borrow::Cow;
+
+use crate::{error::JsonError, parser::parse, scan::Scanner, value::JsonValue, JsonResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl Encoding {
+    // The encoding of a json text and the length of its byte order mark. Without a
+    // BOM the nul bytes around the first (ascii) characters give it away, RFC 4627:
+    //   00 00 00 xx  UTF-32BE
+    //   00 xx 00 xx  UTF-16BE
+    //   xx 00 00 00  UTF-32LE
+    //   xx 00 xx 00  UTF-16LE
+    //   xx xx xx xx  UTF-8
+    pub fn detect(bytes: &[u8]) -> (Encoding, usize) {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8, 3),
+            [0x00, 0x00, 0xFE, 0xFF, ..] => (Encoding::Utf32Be, 4),
+            [0xFF, 0xFE, 0x00, 0x00, ..] => (Encoding::Utf32Le, 4),
+            [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+            [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+            [0, 0, 0, _, ..] => (Encoding::Utf32Be, 0),
+            [_, 0, 0, 0, ..] => (Encoding::Utf32Le, 0),
+            [0, _, ..] => (Encoding::Utf16Be, 0),
+            [_, 0, ..] => (Encoding::Utf16Le, 0),
+            _ => (Encoding::Utf8, 0),
+        }
+    }
+}
+
+// Transcode to utf-8, borrowing when the input already is. Errors carry the byte
+// offset of the invalid sequence in `bytes`.
+pub(crate) fn decode(bytes: &[u8]) -> JsonResult<Cow<'_, str>> {
+    let (encoding, bom) = Encoding::detect(bytes);
+    let body = &bytes[bom..];
+    match encoding {
+        Encoding::Utf8 => match Scanner::detect().validate_utf8(body) {
+            // SAFETY: validated just above
+            Ok(()) => Ok(Cow::Borrowed(unsafe { std::str::from_utf8_unchecked(body) })),
+            Err(offset) => Err(JsonError::InvalidEncoding(bom + offset)),
+        },
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let unit = |pair: &[u8]| match encoding {
+                Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                _ => u16::from_be_bytes([pair[0], pair[1]]),
+            };
+            let mut text = String::with_capacity(body.len() / 2);
+            let mut offset = bom;
+            for ch in char::decode_utf16(body.chunks_exact(2).map(unit)) {
+                match ch {
+                    Ok(ch) => {
+                        text.push(ch);
+                        offset += ch.len_utf16() * 2;
+                    }
+                    Err(_) => return Err(JsonError::InvalidEncoding(offset)),
+                }
+            }
+            // a trailing odd byte was read as half a unit
+            if !body.len().is_multiple_of(2) {
+                return Err(JsonError::InvalidEncoding(bytes.len() - 1));
+            }
+            Ok(Cow::Owned(text))
+        }
+        Encoding::Utf32Le | Encoding::Utf32Be => {
+            if !body.len().is_multiple_of(4) {
+                return Err(JsonError::InvalidEncoding(bytes.len() - body.len() % 4));
+            }
+            body.chunks_exact(4)
+                .enumerate()
+                .map(|(i, quad)| {
+                    let quad = [quad[0], quad[1], quad[2], quad[3]];
+                    let n = match encoding {
+                        Encoding::Utf32Le => u32::from_le_bytes(quad),
+                        _ => u32::from_be_bytes(quad),
+                    };
+                    char::from_u32(n).ok_or(JsonError::InvalidEncoding(bom + i * 4))
+                })
+                .collect::<JsonResult<String>>()
+                .map(Cow::Owned)
+        }
+    }
+}
+
+// `parse` for raw bytes in any of the encodings RFC 4627 allows, with or without a BOM.
+// Error offsets are in `bytes`, like those of `decode`.
+pub fn parse_bytes(bytes: &[u8]) -> JsonResult<JsonValue> {
+    let text = decode(bytes)?;
+    parse(&text).map_err(|e| e.map_offset(|offset| input_offset(bytes, &text, offset)))
+}
+
+// where byte `offset` of the decoded text came from in `bytes`
+fn input_offset(bytes: &[u8], text: &str, offset: usize) -> usize {
+    let (encoding, bom) = Encoding::detect(bytes);
+    let before = &text[..offset.min(text.len())];
+    bom + match encoding {
+        Encoding::Utf8 => before.len(),
+        Encoding::Utf16Le | Encoding::Utf16Be => before.encode_utf16().count() * 2,
+        Encoding::Utf32Le | Encoding::Utf32Be => before.chars().count() * 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, object};
+
+    fn utf16(s: &str, le: bool) -> Vec<u8> {
+        s.encode_utf16()
+            .flat_map(|u| if le { u.to_le_bytes() } else { u.to_be_bytes() })
+            .collect()
+    }
+
+    fn utf32(s: &str, le: bool) -> Vec<u8> {
+        s.chars()
+            .flat_map(|c| if le { (c as u32).to_le_bytes() } else { (c as u32).to_be_bytes() })
+            .collect()
+    }
+
+    #[test]
+    fn detect() {
+        let s = r#"{"a": ["é", "𝄞"]}"#;
+        let expected = object! {"a" => array!["é", "𝄞"]};
+        let mut bom_utf8 = vec![0xEF, 0xBB, 0xBF];
+        bom_utf8.extend_from_slice(s.as_bytes());
+        let mut bom_utf16 = vec![0xFF, 0xFE];
+        bom_utf16.extend(utf16(s, true));
+
+        let inputs = [
+            (s.as_bytes().to_vec(), Encoding::Utf8),
+            (bom_utf8, Encoding::Utf8),
+            (utf16(s, true), Encoding::Utf16Le),
+            (utf16(s, false), Encoding::Utf16Be),
+            (bom_utf16, Encoding::Utf16Le),
+            (utf32(s, true), Encoding::Utf32Le),
+            (utf32(s, false), Encoding::Utf32Be),
+        ];
+        for (bytes, encoding) in inputs {
+            assert_eq!(Encoding::detect(&bytes).0, encoding);
+            assert_eq!(parse_bytes(&bytes), Ok(expected.clone()));
+        }
+        // short documents
+        assert_eq!(parse_bytes(&utf16("1", false)), Ok(1.into()));
+        assert_eq!(parse_bytes(&utf32("2", true)), Ok(2.into()));
+    }
+
+    #[test]
+    fn invalid_sequences() {
+        assert_eq!(parse_bytes(b"[\"a\xFFb\"]"), Err(JsonError::InvalidEncoding(3)));
+        assert_eq!(parse_bytes(b"\xEF\xBB\xBF\"\xC3\""), Err(JsonError::InvalidEncoding(4)));
+
+        // a lone high surrogate
+        let mut bytes = utf16("[\"", true);
+        bytes.extend_from_slice(&[0x34, 0xD8]);
+        bytes.extend(utf16("\"]", true));
+        assert_eq!(parse_bytes(&bytes), Err(JsonError::InvalidEncoding(4)));
+
+        let mut bytes = utf16("[1]", false);
+        bytes.push(0);
+        assert_eq!(parse_bytes(&bytes), Err(JsonError::InvalidEncoding(6)));
+
+        let mut bytes = utf32("[1]", true);
+        bytes[4..8].copy_from_slice(&0x110000u32.to_le_bytes());
+        assert_eq!(parse_bytes(&bytes), Err(JsonError::InvalidEncoding(4)));
+        assert_eq!(
+            JsonError::InvalidEncoding(4).to_string(),
+            "invalid encoding at byte 4"
+        );
+    }
+
+    #[test]
+    fn syntax_error_offsets() {
+        let s = "[\"é\", \"\\ud800\"]";
+        let err = |bytes: &[u8]| parse_bytes(bytes).unwrap_err();
+        assert_eq!(err(s.as_bytes()), JsonError::UnpairedSurrogate(8));
+        let mut bom_utf8 = vec![0xEF, 0xBB, 0xBF];
+        bom_utf8.extend_from_slice(s.as_bytes());
+        assert_eq!(err(&bom_utf8), JsonError::UnpairedSurrogate(11));
+        assert_eq!(err(&utf16(s, true)), JsonError::UnpairedSurrogate(14));
+        assert_eq!(err(&utf32(s, false)), JsonError::UnpairedSurrogate(28));
+        assert_eq!(err(&utf16("[1 2]", false)), JsonError::UnexpectedToken("2".into(), 6));
+    }
+}
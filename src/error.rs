@@ -1,34 +1,251 @@
-use crate::tokenizer::Token;
+use std::{fmt::Display, io};
+
+use crate::{tokenizer::Token, value::JsonValue};
 
 #[derive(Debug, PartialEq)]
 pub enum JsonError {
-    UnexpectedToken(String),
+    // the token as json text and the byte offset it starts at
+    UnexpectedToken(String, usize),
     UnexpectedEndOfJson,
     InvalidType(String),
     UndefinedField(String),
-    UnexpectedCharacter(char),
-    InvalidNumber,
+    // the character and its byte offset
+    UnexpectedCharacter(char, usize),
+    // byte offset of the number
+    InvalidNumber(usize),
     ParsingFailed(String),
+    Io(io::ErrorKind, String),
+    DuplicateKey(String),
+    // byte offset of a sequence that is not valid in the detected encoding
+    InvalidEncoding(usize),
+    // byte offset of the `\u` escape of a surrogate without its other half
+    UnpairedSurrogate(usize),
+    // byte offset of a `\u` escape that isn't followed by four hex digits
+    InvalidUnicodeEscape(usize),
+    // the error of one record in a line based stream, 1-based line number
+    Line(usize, Box<JsonError>),
+    // a path that names no place in a document, or two that can't both be there
+    InvalidPath(String),
+    // a document past a size limit, which limit
+    TooLarge(String),
+}
+
+// what went wrong, in the terms a caller acts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    // malformed input, more bytes won't help
+    Syntax,
+    // the input stopped early, more bytes may complete it
+    Eof,
+    // well formed json that doesn't match the expected shape
+    Data,
+    // reading the input failed
+    Io,
 }
 
 impl JsonError {
-    pub fn unexpected_token(token: Token) -> Self {
-        JsonError::UnexpectedToken(format!("{:?}", token))
+    pub fn unexpected_token(token: Token, offset: usize) -> Self {
+        JsonError::UnexpectedToken(token.to_string(), offset)
     }
 
     pub fn invalid_type(typ: String) -> Self {
-        JsonError::InvalidType(typ.into())
+        JsonError::InvalidType(typ)
     }
 
     pub fn undefined_field(field: String) -> Self {
-        JsonError::UndefinedField(field.into())
+        JsonError::UndefinedField(field)
+    }
+
+    pub fn unexpected_character(byte: u8, offset: usize) -> Self {
+        JsonError::UnexpectedCharacter(char::from_u32(byte as u32).unwrap_or('?'), offset)
+    }
+
+    pub fn expected(typ: &str, found: &JsonValue) -> Self {
+        JsonError::InvalidType(format!("expected {}, found {}", typ, found.type_name()))
+    }
+
+    // prefix the field path carried by `UndefinedField` / `InvalidType`
+    // "name" -> "user.name", "expected string, found null" -> "name: expected string, found null"
+    pub fn at_field(self, field: &str) -> Self {
+        self.at_path(field)
+    }
+
+    pub fn at_index(self, index: usize) -> Self {
+        self.at_path(&format!("[{}]", index))
     }
 
-    pub fn unexpected_character(byte: u8) -> Self {
-        JsonError::UnexpectedCharacter(char::from_u32(byte as u32).unwrap_or('?'))
+    fn at_path(self, segment: &str) -> Self {
+        fn join(segment: &str, rest: &str) -> String {
+            if rest.starts_with('[') {
+                format!("{}{}", segment, rest)
+            } else {
+                format!("{}.{}", segment, rest)
+            }
+        }
+
+        match self {
+            JsonError::UndefinedField(field) => JsonError::UndefinedField(join(segment, &field)),
+            JsonError::InvalidType(msg) if msg.starts_with("expected") => {
+                JsonError::InvalidType(format!("{}: {}", segment, msg))
+            }
+            JsonError::InvalidType(msg) => JsonError::InvalidType(join(segment, &msg)),
+            err => err,
+        }
+    }
+
+    // the byte offset of the input the error is at, for the errors that know it
+    pub fn offset(&self) -> Option<usize> {
+        match *self {
+            JsonError::UnexpectedToken(_, offset)
+            | JsonError::UnexpectedCharacter(_, offset)
+            | JsonError::InvalidNumber(offset)
+            | JsonError::InvalidEncoding(offset)
+            | JsonError::UnpairedSurrogate(offset)
+            | JsonError::InvalidUnicodeEscape(offset) => Some(offset),
+            JsonError::Line(_, ref err) => err.offset(),
+            _ => None,
+        }
     }
 
-    pub fn parsing_faild(err: &str) -> Self {
+    // move the byte offset an error carries, for input decoded or cut out of a larger one
+    pub(crate) fn map_offset(self, f: impl FnOnce(usize) -> usize) -> Self {
+        match self {
+            JsonError::UnexpectedToken(token, offset) => JsonError::UnexpectedToken(token, f(offset)),
+            JsonError::UnexpectedCharacter(ch, offset) => JsonError::UnexpectedCharacter(ch, f(offset)),
+            JsonError::InvalidNumber(offset) => JsonError::InvalidNumber(f(offset)),
+            JsonError::InvalidEncoding(offset) => JsonError::InvalidEncoding(f(offset)),
+            JsonError::UnpairedSurrogate(offset) => JsonError::UnpairedSurrogate(f(offset)),
+            JsonError::InvalidUnicodeEscape(offset) => JsonError::InvalidUnicodeEscape(f(offset)),
+            err => err,
+        }
+    }
+
+    pub fn at_line(self, line: usize) -> Self {
+        JsonError::Line(line, Box::new(self))
+    }
+
+    pub fn parsing_failed(err: &str) -> Self {
         JsonError::ParsingFailed(err.to_string())
     }
+
+    pub fn category(&self) -> Category {
+        match *self {
+            JsonError::UnexpectedToken(..)
+            | JsonError::UnexpectedCharacter(..)
+            | JsonError::InvalidNumber(_)
+            | JsonError::ParsingFailed(_)
+            | JsonError::InvalidEncoding(_)
+            | JsonError::UnpairedSurrogate(_)
+            | JsonError::InvalidUnicodeEscape(_) => Category::Syntax,
+            JsonError::UnexpectedEndOfJson => Category::Eof,
+            JsonError::InvalidType(_)
+            | JsonError::UndefinedField(_)
+            | JsonError::DuplicateKey(_)
+            | JsonError::InvalidPath(_)
+            | JsonError::TooLarge(_) => Category::Data,
+            JsonError::Io(..) => Category::Io,
+            JsonError::Line(_, ref err) => err.category(),
+        }
+    }
+
+    pub fn is_syntax(&self) -> bool {
+        self.category() == Category::Syntax
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.category() == Category::Eof
+    }
+
+    pub fn is_data(&self) -> bool {
+        self.category() == Category::Data
+    }
+
+    pub fn is_io(&self) -> bool {
+        self.category() == Category::Io
+    }
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            JsonError::UnexpectedToken(ref token, offset) => {
+                write!(f, "unexpected token `{}` at byte {}", token, offset)
+            }
+            JsonError::UnexpectedEndOfJson => f.write_str("unexpected end of json"),
+            JsonError::InvalidType(ref msg) => write!(f, "invalid type: {}", msg),
+            JsonError::UndefinedField(ref field) => write!(f, "missing field `{}`", field),
+            JsonError::UnexpectedCharacter(ch, offset) => {
+                write!(f, "unexpected character {:?} at byte {}", ch, offset)
+            }
+            JsonError::InvalidNumber(offset) => write!(f, "invalid number at byte {}", offset),
+            JsonError::ParsingFailed(ref msg) => write!(f, "parsing failed: {}", msg),
+            JsonError::Io(_, ref msg) => write!(f, "i/o error: {}", msg),
+            JsonError::DuplicateKey(ref key) => write!(f, "duplicate key `{}`", key),
+            JsonError::InvalidEncoding(offset) => write!(f, "invalid encoding at byte {}", offset),
+            JsonError::UnpairedSurrogate(offset) => write!(f, "unpaired surrogate at byte {}", offset),
+            JsonError::InvalidUnicodeEscape(offset) => {
+                write!(f, "invalid unicode escape at byte {}", offset)
+            }
+            JsonError::Line(line, ref err) => write!(f, "line {}: {}", line, err),
+            JsonError::InvalidPath(ref msg) => write!(f, "invalid path {}", msg),
+            JsonError::TooLarge(ref msg) => write!(f, "too large: {}", msg),
+        }
+    }
+}
+
+// `Line` has no `source`, its message already ends with the inner one
+impl std::error::Error for JsonError {}
+
+impl From<io::Error> for JsonError {
+    fn from(err: io::Error) -> Self {
+        JsonError::Io(err.kind(), err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn display() {
+        assert_eq!(parse("[1,").unwrap_err().to_string(), "unexpected end of json");
+        assert_eq!(parse("[1 2]").unwrap_err().to_string(), "unexpected token `2` at byte 3");
+        assert_eq!(
+            parse(r#"{"a" "b\n"}"#).unwrap_err().to_string(),
+            r#"unexpected token `"b\n"` at byte 5"#
+        );
+        assert_eq!(parse("[x]").unwrap_err().to_string(), "unexpected character 'x' at byte 1");
+        assert_eq!(
+            JsonError::undefined_field("a.b".to_string()).to_string(),
+            "missing field `a.b`"
+        );
+    }
+
+    #[test]
+    fn category() {
+        assert_eq!(parse(r#"{"a": "#).unwrap_err().category(), Category::Eof);
+        assert!(parse(r#"{"a" 1}"#).unwrap_err().is_syntax());
+        // a number cut off by the end of the input could still be completed
+        for s in ["[1", "[-", "[1e", "[1.", "[1.5E-"] {
+            assert!(parse(s).unwrap_err().is_eof(), "{}", s);
+        }
+        assert_eq!(parse("[1e]"), Err(JsonError::InvalidNumber(1)));
+        assert!(parse("[1e]").unwrap_err().is_syntax());
+        assert!(JsonError::expected("string", &JsonValue::Null).is_data());
+        let io = io::Error::new(io::ErrorKind::UnexpectedEof, "closed");
+        assert!(JsonError::from(io).is_io());
+    }
+
+    #[test]
+    fn boxed_error() {
+        fn run() -> Result<JsonValue, Box<dyn std::error::Error>> {
+            Ok(parse("nul")?)
+        }
+        assert_eq!(run().unwrap_err().to_string(), "unexpected end of json");
+
+        let err = JsonError::UnexpectedEndOfJson.at_line(3);
+        assert_eq!(err.to_string(), "line 3: unexpected end of json");
+        assert!(std::error::Error::source(&err).is_none());
+    }
 }
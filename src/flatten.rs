@@ -0,0 +1,393 @@
+use std::collections::BTreeMap;
+
+use crate::{error::JsonError, value::JsonValue, JsonResult};
+
+// how an array index is written after its parent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IndexStyle {
+    // a.0
+    #[default]
+    Separator,
+    // a[0]
+    Brackets,
+}
+
+#[derive(Debug, Clone)]
+pub struct FlattenOptions {
+    pub separator: String,
+    pub arrays: IndexStyle,
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        FlattenOptions {
+            separator: ".".to_string(),
+            arrays: IndexStyle::Separator,
+        }
+    }
+}
+
+const ESCAPE: char = '\\';
+const EMPTY: &str = "\"\"";
+
+impl FlattenOptions {
+    // a separator paths can be split on again: not empty, free of the escape and of
+    // brackets, and not starting with a digit when indexes are written after it
+    fn check(&self) -> JsonResult<()> {
+        let invalid = |why: &str| {
+            JsonError::InvalidPath(format!("separator `{}` {}", self.separator, why))
+        };
+        if self.separator.is_empty() {
+            return Err(invalid("is empty"));
+        }
+        if self.separator.contains([ESCAPE, '[', ']']) {
+            return Err(invalid("contains `\\`, `[` or `]`"));
+        }
+        let digit = self.separator.starts_with(|ch: char| ch.is_ascii_digit());
+        if digit && self.arrays == IndexStyle::Separator {
+            return Err(invalid("starts with a digit"));
+        }
+        Ok(())
+    }
+}
+
+// {"a": {"b": [1, 2]}, "c.d": {}} -> {"a.b.0": 1, "a.b.1": 2, "c\.d": {}}
+//
+// Leaves are scalars and empty arrays and objects, a leaf root gets the key "". In keys a
+// backslash escapes itself, the first char of the separator and, with `Brackets`, `[`
+// and `]`. With `Separator` an all-digit key starts with a backslash so it isn't read
+// back as an index, and an empty key is written `""` so it isn't read as the root.
+// Fails for a separator that couldn't be told apart from keys when reading back.
+pub fn flatten(
+    value: &JsonValue,
+    options: FlattenOptions,
+) -> JsonResult<BTreeMap<String, JsonValue>> {
+    options.check()?;
+    let mut out = BTreeMap::new();
+    flatten_into(&mut String::new(), value, &options, &mut out);
+    Ok(out)
+}
+
+fn flatten_into(
+    path: &mut String,
+    value: &JsonValue,
+    options: &FlattenOptions,
+    out: &mut BTreeMap<String, JsonValue>,
+) {
+    let len = path.len();
+    match value {
+        JsonValue::Array(array) if !array.is_empty() => {
+            for (i, item) in array.iter().enumerate() {
+                match options.arrays {
+                    IndexStyle::Separator => {
+                        if len > 0 {
+                            path.push_str(&options.separator);
+                        }
+                        path.push_str(&i.to_string());
+                    }
+                    IndexStyle::Brackets => {
+                        path.push('[');
+                        path.push_str(&i.to_string());
+                        path.push(']');
+                    }
+                }
+                flatten_into(path, item, options, out);
+                path.truncate(len);
+            }
+        }
+        JsonValue::Object(object) if !object.is_empty() => {
+            for (key, item) in object {
+                if len > 0 {
+                    path.push_str(&options.separator);
+                }
+                escape_key(path, key, options);
+                flatten_into(path, item, options, out);
+                path.truncate(len);
+            }
+        }
+        leaf => {
+            out.insert(path.clone(), leaf.clone());
+        }
+    }
+}
+
+fn escape_key(path: &mut String, key: &str, options: &FlattenOptions) {
+    let brackets = options.arrays == IndexStyle::Brackets;
+    match key {
+        "" => return path.push_str(EMPTY),
+        EMPTY => path.push(ESCAPE),
+        _ if !brackets && key.bytes().all(|b| b.is_ascii_digit()) => path.push(ESCAPE),
+        _ => (),
+    }
+    for ch in key.chars() {
+        let special = ch == ESCAPE
+            || options.separator.starts_with(ch)
+            || (brackets && (ch == '[' || ch == ']'));
+        if special {
+            path.push(ESCAPE);
+        }
+        path.push(ch);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn parse_path(path: &str, options: &FlattenOptions) -> JsonResult<Vec<Segment>> {
+    let invalid = |why: &str| JsonError::InvalidPath(format!("`{}`: {}", path, why));
+    let brackets = options.arrays == IndexStyle::Brackets;
+    let mut segments = Vec::new();
+    if path.is_empty() {
+        return Ok(segments);
+    }
+
+    let mut rest = path;
+    loop {
+        // a key, up to the next separator or `[`
+        let mut key = String::new();
+        let mut escaped = false;
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next() {
+                None => break rest.len(),
+                Some((i, _)) if rest[i..].starts_with(options.separator.as_str()) => break i,
+                Some((i, '[')) if brackets => break i,
+                Some((_, ESCAPE)) => {
+                    escaped = true;
+                    key.extend(chars.next().map(|(_, ch)| ch));
+                }
+                Some((_, ch)) => key.push(ch),
+            }
+        };
+        if &rest[..end] == EMPTY {
+            escaped = true;
+            key.clear();
+        }
+        rest = &rest[end..];
+
+        let digits = !key.is_empty() && key.bytes().all(|b| b.is_ascii_digit());
+        if digits && !brackets && !escaped {
+            segments.push(Segment::Index(key.parse().map_err(|_| invalid("index too large"))?));
+        } else if escaped || !key.is_empty() || !rest.starts_with('[') {
+            segments.push(Segment::Key(key));
+        }
+
+        while brackets && rest.starts_with('[') {
+            let close = rest.find(']').ok_or_else(|| invalid("unclosed `[`"))?;
+            let index = rest[1..close].parse().map_err(|_| invalid("expected an index in `[]`"))?;
+            segments.push(Segment::Index(index));
+            rest = &rest[close + 1..];
+        }
+
+        match rest.strip_prefix(options.separator.as_str()) {
+            Some(next) => rest = next,
+            None if rest.is_empty() => return Ok(segments),
+            None => return Err(invalid("expected a separator after `]`")),
+        }
+    }
+}
+
+// The tree being rebuilt, arrays by index so that "a.10" can come before "a.2". A
+// child is `None` from when its path is first seen until something goes in it.
+enum Node {
+    Leaf(JsonValue),
+    Array(BTreeMap<usize, Option<Node>>),
+    Object(BTreeMap<String, Option<Node>>),
+}
+
+impl Node {
+    fn into_value(node: Option<Node>) -> JsonResult<JsonValue> {
+        Ok(match node {
+            None => JsonValue::Null,
+            Some(Node::Leaf(value)) => value,
+            Some(Node::Object(object)) => JsonValue::Object(
+                object
+                    .into_iter()
+                    .map(|(key, node)| Ok((key, Node::into_value(node)?)))
+                    .collect::<JsonResult<_>>()?,
+            ),
+            Some(Node::Array(array)) => {
+                let mut items = Vec::with_capacity(array.len());
+                for (index, node) in array {
+                    if index != items.len() {
+                        let msg = format!("index {} without index {}", index, items.len());
+                        return Err(JsonError::InvalidPath(msg));
+                    }
+                    items.push(Node::into_value(node)?);
+                }
+                JsonValue::Array(items)
+            }
+        })
+    }
+}
+
+// The inverse of `flatten` with the same options. Paths that overlap (`a` and `a.b`),
+// mix keys and indexes under one parent, or leave holes in an array are errors.
+pub fn unflatten(
+    map: &BTreeMap<String, JsonValue>,
+    options: FlattenOptions,
+) -> JsonResult<JsonValue> {
+    options.check()?;
+    if map.is_empty() {
+        return Ok(JsonValue::Object(BTreeMap::new()));
+    }
+    let mut root: Option<Node> = None;
+    for (path, value) in map {
+        let conflict = || JsonError::InvalidPath(format!("`{}` overlaps another path", path));
+        let mut slot = &mut root;
+        for segment in parse_path(path, &options)? {
+            let node = slot.get_or_insert_with(|| match segment {
+                Segment::Key(_) => Node::Object(BTreeMap::new()),
+                Segment::Index(_) => Node::Array(BTreeMap::new()),
+            });
+            slot = match (node, segment) {
+                (Node::Object(object), Segment::Key(key)) => object.entry(key).or_default(),
+                (Node::Array(array), Segment::Index(index)) => array.entry(index).or_default(),
+                _ => return Err(conflict()),
+            };
+        }
+        if slot.is_some() {
+            return Err(conflict());
+        }
+        *slot = Some(Node::Leaf(value.clone()));
+    }
+    Node::into_value(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    fn brackets() -> FlattenOptions {
+        FlattenOptions {
+            arrays: IndexStyle::Brackets,
+            ..Default::default()
+        }
+    }
+
+    fn keys(map: &BTreeMap<String, JsonValue>) -> Vec<&str> {
+        map.keys().map(|k| k.as_str()).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = json!({
+            "a": {"b": [1, 2, {"c": null}]},
+            "d.e": [[], {}],
+            "10": true,
+            "": {"x[0]": "y", "back\\slash": 0}
+        });
+
+        let flat = flatten(&value, FlattenOptions::default()).unwrap();
+        assert_eq!(
+            keys(&flat),
+            [
+                r#""".back\\slash"#,
+                r#""".x[0]"#,
+                r"\10",
+                "a.b.0",
+                "a.b.1",
+                "a.b.2.c",
+                r"d\.e.0",
+                r"d\.e.1",
+            ]
+        );
+        assert_eq!(flat["a.b.1"], JsonValue::from(2));
+        assert_eq!(unflatten(&flat, FlattenOptions::default()), Ok(value.clone()));
+
+        let flat = flatten(&value, brackets()).unwrap();
+        assert_eq!(
+            keys(&flat),
+            [
+                r#""".back\\slash"#,
+                r#""".x\[0\]"#,
+                "10",
+                "a.b[0]",
+                "a.b[1]",
+                "a.b[2].c",
+                r"d\.e[0]",
+                r"d\.e[1]",
+            ]
+        );
+        assert_eq!(unflatten(&flat, brackets()), Ok(value.clone()));
+
+        let slash = FlattenOptions {
+            separator: "::".to_string(),
+            ..Default::default()
+        };
+        let value = json!({"a:": {"b": [1]}});
+        let flat = flatten(&value, slash.clone()).unwrap();
+        assert_eq!(keys(&flat), [r"a\:::b::0"]);
+        assert_eq!(unflatten(&flat, slash), Ok(value));
+    }
+
+    #[test]
+    fn roots() {
+        let empty_keys = json!({"": 1, "\"\"": [2], "a": {"": {"": 3}}});
+        let values = [json!(1), json!([]), json!({}), json!([[1], 2]), json!({"": [1]}), empty_keys];
+        for value in values {
+            let flat = flatten(&value, brackets()).unwrap();
+            assert_eq!(unflatten(&flat, brackets()), Ok(value.clone()));
+            let flat = flatten(&value, Default::default()).unwrap();
+            assert_eq!(unflatten(&flat, Default::default()), Ok(value));
+        }
+        assert_eq!(keys(&flatten(&json!([[1], 2]), brackets()).unwrap()), ["[0][0]", "[1]"]);
+    }
+
+    #[test]
+    fn invalid_maps() {
+        let map = |pairs: &[(&str, i32)]| -> BTreeMap<String, JsonValue> {
+            pairs.iter().map(|&(k, v)| (k.to_string(), v.into())).collect()
+        };
+        let error = |pairs: &[(&str, i32)]| {
+            unflatten(&map(pairs), brackets()).unwrap_err().to_string()
+        };
+
+        assert_eq!(error(&[("a", 1), ("a.b", 2)]), "invalid path `a.b` overlaps another path");
+        assert_eq!(error(&[("a[0]", 1), ("a.b", 2)]), "invalid path `a[0]` overlaps another path");
+        assert_eq!(error(&[("a[1]", 1)]), "invalid path index 1 without index 0");
+        assert_eq!(error(&[("a[x]", 1)]), "invalid path `a[x]`: expected an index in `[]`");
+        assert_eq!(error(&[("a[0", 1)]), "invalid path `a[0`: unclosed `[`");
+        assert_eq!(error(&[("a[0]b", 1)]), "invalid path `a[0]b`: expected a separator after `]`");
+
+        let value = unflatten(&map(&[("a.10", 1), ("a.2", 2)]), Default::default());
+        assert!(value.is_err());
+        let pairs: Vec<_> = (0..11).map(|i| (format!("a.{}", i), i)).collect();
+        let pairs: Vec<_> = pairs.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        let value = unflatten(&map(&pairs), Default::default()).unwrap();
+        assert_eq!(value.pointer("/a/10"), Some(&10.into()));
+    }
+
+    #[test]
+    fn invalid_separators() {
+        let options = |separator: &str, arrays| FlattenOptions {
+            separator: separator.to_string(),
+            arrays,
+        };
+        let error = |separator: &str, arrays| {
+            let flat = flatten(&json!({"a": [1]}), options(separator, arrays)).unwrap_err();
+            let map = BTreeMap::from([("a".to_string(), JsonValue::Null)]);
+            assert_eq!(unflatten(&map, options(separator, arrays)), Err(flat));
+            unflatten(&map, options(separator, arrays)).unwrap_err().to_string()
+        };
+
+        assert_eq!(error("", IndexStyle::Separator), "invalid path separator `` is empty");
+        let message = r"invalid path separator `\` contains `\`, `[` or `]`";
+        assert_eq!(error("\\", IndexStyle::Separator), message);
+        assert!(error("\\.", IndexStyle::Brackets).contains("contains"));
+        assert!(error("[", IndexStyle::Separator).contains("contains"));
+        assert!(error("]", IndexStyle::Brackets).contains("contains"));
+        let message = "invalid path separator `0x` starts with a digit";
+        assert_eq!(error("0x", IndexStyle::Separator), message);
+
+        // indexes are in brackets, so a digit can't be mistaken for one
+        let value = json!({"a": [1], "b": {"c": 2}});
+        let flat = flatten(&value, options("0", IndexStyle::Brackets)).unwrap();
+        assert_eq!(keys(&flat), ["a[0]", "b0c"]);
+        assert_eq!(unflatten(&flat, options("0", IndexStyle::Brackets)), Ok(value));
+    }
+}
@@ -1,5 +1,9 @@
-use crate::value::JsonValue;
-use std::collections::BTreeMap;
+use crate::{
+    number::Number,
+    value::JsonValue,
+    wtf8::{Wtf8Chunk, Wtf8String},
+};
+use std::{collections::BTreeMap, fmt::Write};
 
 // r#"
 //     {
@@ -59,9 +63,11 @@ impl Generator {
                 false => self.write("false"),
             },
             JsonValue::String(s) => self.write_string(s),
-            JsonValue::Number(n) => self.write(n.to_string().as_str()),
+            JsonValue::Wtf8(s) => self.write_wtf8(s),
+            JsonValue::Number(n) => self.write_number(n),
             JsonValue::Array(array) => self.write_array(array),
             JsonValue::Object(object) => self.write_object(object),
+            JsonValue::Raw(raw) => self.write(raw),
         }
     }
 
@@ -85,33 +91,45 @@ impl Generator {
         }
         if !self.minify {
             self.ln();
-            for _ in 0..(self.dent * self.spaces) {
-                self.write(" ");
-            }
+            let width = self.dent as usize * self.spaces as usize;
+            self.code.extend(std::iter::repeat_n(' ', width));
+        }
+    }
+
+    // NaN and infinities have no JSON representation
+    // floats keep a fraction or an exponent (`{:?}` gives "1.0" and "1e300") so they
+    // read back as floats, "-0" already does
+    fn write_number(&mut self, n: &Number) {
+        match n {
+            Number::Float(f) if !f.is_finite() => self.write("null"),
+            Number::Float(f) if *f == 0.0 && f.is_sign_negative() => self.write("-0"),
+            Number::Float(f) => write!(self.code, "{:?}", f).unwrap(),
+            _ => write!(self.code, "{}", n).unwrap(),
         }
     }
 
-    fn write_string(&mut self, s: &String) {
+    fn write_string(&mut self, s: &str) {
         self.write("\"");
+        self.write_escaped(s);
+        self.write("\"");
+    }
 
-        for ch in s.chars() {
-            match ch {
-                '\\' | '"' => {
-                    self.write("\\");
-                    self.write(&ch.to_string());
-                }
-                '\n' => self.write("\\n"),
-                '\r' => self.write("\\r"),
-                '\t' => self.write("\\t"),
-                '\u{000C}' => self.write("\\f"),
-                '\u{0008}' => self.write("\\b"),
-                _ => self.write(&ch.to_string()),
+    // lone surrogates go back out as the `\uXXXX` escapes they came from
+    fn write_wtf8(&mut self, s: &Wtf8String) {
+        self.write("\"");
+        for chunk in s.chunks() {
+            match chunk {
+                Wtf8Chunk::Str(s) => self.write_escaped(s),
+                Wtf8Chunk::Surrogate(unit) => write!(self.code, "\\u{:04x}", unit).unwrap(),
             }
         }
-
         self.write("\"");
     }
 
+    fn write_escaped(&mut self, s: &str) {
+        escape_into(&mut self.code, s);
+    }
+
     // [1,2,3]
     // [
     //     1,
@@ -158,7 +176,7 @@ impl Generator {
                 self.write(",");
                 self.new_line(Tab::Stay);
             };
-            self.write(&format!("{:?}", key));
+            self.write_string(key);
             self.write(":");
             if !self.minify {
                 self.write(" ");
@@ -170,6 +188,32 @@ impl Generator {
     }
 }
 
+// copy runs of plain bytes at once, only escapes are written one by one
+pub(crate) fn escape_into(code: &mut String, s: &str) {
+    let bytes = s.as_bytes();
+    let mut start = 0;
+    for (i, &ch) in bytes.iter().enumerate() {
+        let escaped = match ch {
+            b'\\' => Some("\\\\"),
+            b'"' => Some("\\\""),
+            b'\n' => Some("\\n"),
+            b'\r' => Some("\\r"),
+            b'\t' => Some("\\t"),
+            0x0C => Some("\\f"),
+            0x08 => Some("\\b"),
+            0x00..=0x1F => None,
+            _ => continue,
+        };
+        code.push_str(&s[start..i]);
+        match escaped {
+            Some(escaped) => code.push_str(escaped),
+            None => write!(code, "\\u{:04x}", ch).unwrap(),
+        }
+        start = i + 1;
+    }
+    code.push_str(&s[start..]);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -250,7 +294,7 @@ mod tests {
     #[test]
     fn write_escaped_string() {
         let json = r#" "\u67e5" "#;
-        let obj = parse(&json).unwrap();
+        let obj = parse(json).unwrap();
         println!("{:?}", obj);
     }
 
@@ -259,6 +303,15 @@ mod tests {
         assert_eq!(stringify("\r\n\t\u{8}\u{c}\\\""), r#""\r\n\t\b\f\\\"""#);
     }
 
+    #[test]
+    fn stringify_control_characters() {
+        assert_eq!(stringify("a\u{1}b\u{1f}"), r#""a\u0001b\u001f""#);
+        assert_eq!(
+            stringify(object! {"k\"\n" => "é"}),
+            r#"{"k\"\n":"é"}"#
+        );
+    }
+
     #[test]
     fn parse_escaped_unicode() {
         let data = parse(r#" "\u2764\ufe0f\t\n\n\n\n" "#).unwrap();
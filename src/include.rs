@@ -0,0 +1,274 @@
+use std::fmt::Display;
+
+#[cfg(feature = "regex")]
+use regex_lite::Regex;
+
+use crate::value::{escape_pointer, JsonValue};
+
+// Placeholders for the expected side of `json_include`, single-key objects so they
+// fit in `object!`/`array!`. An expected object with exactly one of these keys is
+// always read as the placeholder, wrap it in `literal` to expect it as it is.
+const ANY: &str = "$any";
+#[cfg(feature = "regex")]
+const MATCHES: &str = "$matches";
+const APPROX: &str = "$approx";
+const LITERAL: &str = "$literal";
+
+// any value, as long as it is there
+pub fn any() -> JsonValue {
+    JsonValue::Object([(ANY.to_string(), true.into())].into())
+}
+
+// a string matching a regex-lite pattern, unanchored like `Regex::is_match`
+#[cfg(feature = "regex")]
+pub fn matching(pattern: &str) -> JsonValue {
+    JsonValue::Object([(MATCHES.to_string(), pattern.into())].into())
+}
+
+// a number within `tolerance` of `value`
+pub fn approx(value: f64, tolerance: f64) -> JsonValue {
+    JsonValue::Object([(APPROX.to_string(), vec![value, tolerance].into())].into())
+}
+
+// `expected` with no placeholders read inside it, for objects that have their keys
+pub fn literal(expected: JsonValue) -> JsonValue {
+    JsonValue::Object([(LITERAL.to_string(), expected)].into())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    // RFC 6901 pointer into the actual value
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "(root): {}", self.message),
+            false => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+// Where `actual` does not contain `expected`. Objects may have more keys and arrays
+// more items at the end than expected, everything else has to be equal as in
+// `JsonValue::semantic_eq`, or match the placeholder put in its place.
+pub fn json_include(actual: &JsonValue, expected: &JsonValue) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    let mut include = Include { out: &mut mismatches, literal: false };
+    include.value(String::new(), actual, expected);
+    mismatches
+}
+
+struct Include<'a> {
+    out: &'a mut Vec<Mismatch>,
+    // inside `literal`, placeholder keys are plain keys
+    literal: bool,
+}
+
+impl Include<'_> {
+    fn value(&mut self, path: String, actual: &JsonValue, expected: &JsonValue) {
+        let message = match (actual, expected) {
+            (actual, JsonValue::Object(object)) if object.len() == 1 && !self.literal => {
+                let (key, arg) = object.iter().next().unwrap();
+                match key.as_str() {
+                    ANY => None,
+                    #[cfg(feature = "regex")]
+                    MATCHES => matches(actual, arg),
+                    APPROX => near(actual, arg),
+                    LITERAL => {
+                        self.literal = true;
+                        self.value(path, actual, arg);
+                        self.literal = false;
+                        return;
+                    }
+                    _ => return self.members(path, actual, expected),
+                }
+            }
+            (JsonValue::Object(_), JsonValue::Object(_)) => {
+                return self.members(path, actual, expected)
+            }
+            (JsonValue::Array(actual), JsonValue::Array(expected)) => {
+                for (i, item) in expected.iter().enumerate() {
+                    let path = format!("{}/{}", path, i);
+                    match actual.get(i) {
+                        Some(found) => self.value(path, found, item),
+                        None => self.out.push(Mismatch {
+                            path,
+                            message: format!("missing item {}", item.dump()),
+                        }),
+                    }
+                }
+                None
+            }
+            (actual, expected) if actual.type_name() != expected.type_name() => Some(format!(
+                "expected {}, found {}",
+                expected.type_name(),
+                actual.type_name()
+            )),
+            (actual, expected) if !actual.semantic_eq(expected) => {
+                Some(format!("expected {}, found {}", expected.dump(), actual.dump()))
+            }
+            _ => None,
+        };
+        if let Some(message) = message {
+            self.out.push(Mismatch { path, message });
+        }
+    }
+
+    fn members(&mut self, path: String, actual: &JsonValue, expected: &JsonValue) {
+        let (JsonValue::Object(actual), JsonValue::Object(expected)) = (actual, expected) else {
+            let message = format!("expected object, found {}", actual.type_name());
+            return self.out.push(Mismatch { path, message });
+        };
+        for (key, value) in expected {
+            let path = format!("{}/{}", path, escape_pointer(key));
+            match actual.get(key) {
+                Some(found) => self.value(path, found, value),
+                None => self.out.push(Mismatch {
+                    path,
+                    message: "missing key".to_string(),
+                }),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "regex")]
+fn matches(actual: &JsonValue, pattern: &JsonValue) -> Option<String> {
+    let JsonValue::String(pattern) = pattern else {
+        return Some(format!("`{}` takes a string pattern", MATCHES));
+    };
+    let regex = match Regex::new(pattern) {
+        Ok(regex) => regex,
+        Err(e) => return Some(format!("invalid pattern `{}`: {}", pattern, e)),
+    };
+    match actual {
+        JsonValue::String(s) if regex.is_match(s) => None,
+        JsonValue::Wtf8(s) if regex.is_match(&s.to_string_lossy()) => None,
+        JsonValue::String(_) | JsonValue::Wtf8(_) => {
+            Some(format!("expected a string matching `{}`, found {}", pattern, actual.dump()))
+        }
+        _ => Some(format!("expected string, found {}", actual.type_name())),
+    }
+}
+
+fn near(actual: &JsonValue, arg: &JsonValue) -> Option<String> {
+    let (value, tolerance) = match arg {
+        JsonValue::Array(arg) => match arg.as_slice() {
+            [JsonValue::Number(value), JsonValue::Number(tolerance)] => {
+                (value.as_f64(), tolerance.as_f64())
+            }
+            _ => return Some(format!("`{}` takes [value, tolerance]", APPROX)),
+        },
+        _ => return Some(format!("`{}` takes [value, tolerance]", APPROX)),
+    };
+    match actual {
+        JsonValue::Number(n) if (n.as_f64() - value).abs() <= tolerance => None,
+        JsonValue::Number(n) => Some(format!("expected {} ± {}, found {}", value, tolerance, n)),
+        _ => Some(format!("expected number, found {}", actual.type_name())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, object};
+
+    fn messages(actual: &JsonValue, expected: &JsonValue) -> Vec<String> {
+        json_include(actual, expected).iter().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn subset() {
+        let actual = object! {
+            "id" => 7,
+            "name" => "ada",
+            "tags" => array!["a", "b", "c"],
+            "meta" => object! {"score" => 0.5, "created" => "2024-01-02"}
+        };
+        let expected = object! {"id" => 7.0, "tags" => array!["a", "b"], "meta" => object! {}};
+        assert!(json_include(&actual, &expected).is_empty());
+
+        let expected = object! {
+            "id" => "7",
+            "name" => "bob",
+            "tags" => array!["a", "b", "c", "d"],
+            "meta" => object! {"missing" => 1},
+            "a/b" => 1
+        };
+        assert_eq!(
+            messages(&actual, &expected),
+            vec![
+                "/a~1b: missing key",
+                "/id: expected string, found number",
+                "/meta/missing: missing key",
+                r#"/name: expected "bob", found "ada""#,
+                r#"/tags/3: missing item "d""#,
+            ]
+        );
+        assert_eq!(messages(&array![], &object! {}), vec!["(root): expected object, found array"]);
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn placeholders() {
+        let actual = object! {
+            "id" => "c0ffee",
+            "at" => JsonValue::Null,
+            "score" => 0.30000000000000004,
+            "items" => array![object! {"id" => 1}]
+        };
+        let expected = object! {
+            "id" => matching("^[0-9a-f]+$"),
+            "at" => any(),
+            "score" => approx(0.3, 1e-9),
+            "items" => array![any()]
+        };
+        assert!(json_include(&actual, &expected).is_empty());
+
+        let expected = object! {
+            "id" => matching("^[0-9]+$"),
+            "at" => matching("("),
+            "score" => approx(1.0, 0.1),
+            "missing" => any()
+        };
+        let found = messages(&actual, &expected);
+        assert!(found[0].starts_with("/at: invalid pattern `(`"), "{}", found[0]);
+        assert_eq!(
+            found[1..],
+            [
+                "/id: expected a string matching `^[0-9]+$`, found \"c0ffee\"",
+                "/missing: missing key",
+                "/score: expected 1 ± 0.1, found 0.30000000000000004",
+            ]
+        );
+    }
+
+    #[test]
+    fn literal_placeholder_keys() {
+        let actual = object! {"$any" => true, "n" => object! {"$approx" => array![1, 2]}};
+        assert!(json_include(&array![1], &any()).is_empty());
+        assert!(json_include(&actual, &literal(actual.clone())).is_empty());
+        let expected = object! {"n" => literal(object! {"$approx" => array![1]})};
+        assert!(json_include(&actual, &expected).is_empty());
+
+        let expected = literal(object! {"$any" => false});
+        assert_eq!(messages(&actual, &expected), vec!["/$any: expected false, found true"]);
+        // without `literal` it is the placeholder and matches anything
+        assert!(json_include(&JsonValue::Null, &object! {"$any" => false}).is_empty());
+    }
+
+    #[test]
+    fn assert_json_include() {
+        let actual = object! {"a" => 1, "b" => 2};
+        crate::assert_json_include!(actual: actual, expected: object! {"a" => 1});
+        crate::assert_json_include!(expected: object! {}, actual: actual);
+        let result = std::panic::catch_unwind(|| {
+            crate::assert_json_include!(actual: array![1], expected: array![2]);
+        });
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.ends_with("/0: expected 2, found 1"), "{}", message);
+    }
+}
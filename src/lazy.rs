@@ -0,0 +1,325 @@
+use std::borrow::Cow;
+
+use crate::{
+    error::JsonError,
+    parser::parse,
+    scan::Scanner,
+    tokenizer::{Token, Tokenizer},
+    value::{pointer_index, pointer_tokens, JsonValue},
+    JsonResult,
+};
+
+// an object or array of the source
+#[derive(Debug, Clone, Copy)]
+struct Container {
+    // just past the closing bracket
+    end: usize,
+    // the first container after this one that isn't inside it
+    next: usize,
+}
+
+// A validated document that only remembers where its objects and arrays are. Values
+// are read from the source when they are asked for: `get` and `at` tokenize one level
+// of a container and jump over the containers in it, `to_value` parses one subtree
+// and `raw` hands out its text as it is in the source.
+#[derive(Debug)]
+pub struct LazyDocument<'a> {
+    source: &'a str,
+    // in document order, so the containers inside one follow it
+    containers: Vec<Container>,
+    scanner: Scanner,
+    root: (usize, usize),
+}
+
+// Read `json` once to check it and record its containers. Anything `parse` rejects is
+// rejected here with the same error.
+pub fn parse_lazy(json: &str) -> JsonResult<LazyDocument<'_>> {
+    let scanner = Scanner::detect();
+    let mut indexer = Indexer {
+        tokenizer: Tokenizer::with_scanner(json, scanner),
+        containers: Vec::new(),
+    };
+    let token = indexer.tokenizer.next()?;
+    let start = indexer.tokenizer.token_start();
+    indexer.value(token)?;
+    let root = (start, indexer.tokenizer.index());
+    Ok(LazyDocument {
+        source: json,
+        containers: indexer.containers,
+        scanner,
+        root,
+    })
+}
+
+struct Indexer<'a> {
+    tokenizer: Tokenizer<'a>,
+    containers: Vec<Container>,
+}
+
+impl Indexer<'_> {
+    fn value(&mut self, token: Token) -> JsonResult<()> {
+        match token {
+            Token::Null | Token::Boolen(_) | Token::Number(_) | Token::String(_) => Ok(()),
+            Token::BracketOn => self.container(Token::BracketOff),
+            Token::BraceOn => self.container(Token::BraceOff),
+            token => Err(JsonError::unexpected_token(token, self.tokenizer.token_start())),
+        }
+    }
+
+    fn container(&mut self, close: Token<'static>) -> JsonResult<()> {
+        let object = close == Token::BraceOff;
+        let index = self.containers.len();
+        self.containers.push(Container { end: 0, next: 0 });
+
+        let mut token = self.tokenizer.next()?;
+        if token != close {
+            loop {
+                if object {
+                    match token {
+                        Token::String(_) => (),
+                        token => return Err(JsonError::unexpected_token(token, self.tokenizer.token_start())),
+                    }
+                    match self.tokenizer.next()? {
+                        Token::Colon => (),
+                        token => return Err(JsonError::unexpected_token(token, self.tokenizer.token_start())),
+                    }
+                    token = self.tokenizer.next()?;
+                }
+                self.value(token)?;
+                token = match self.tokenizer.next()? {
+                    Token::Comma => self.tokenizer.next()?,
+                    token if token == close => break,
+                    token => return Err(JsonError::unexpected_token(token, self.tokenizer.token_start())),
+                };
+            }
+        }
+
+        self.containers[index].end = self.tokenizer.index();
+        self.containers[index].next = self.containers.len();
+        Ok(())
+    }
+}
+
+impl<'a> LazyDocument<'a> {
+    pub fn root(&self) -> LazyValue<'_, 'a> {
+        let (start, end) = self.root;
+        LazyValue {
+            document: self,
+            start,
+            end,
+            container: 0,
+        }
+    }
+}
+
+// A value of a `LazyDocument`, nothing is decoded until a method needs it. Its text
+// and keys borrow from the source, so they outlive the document.
+#[derive(Clone, Copy)]
+pub struct LazyValue<'doc, 'src> {
+    document: &'doc LazyDocument<'src>,
+    start: usize,
+    end: usize,
+    // index in `containers`, for objects and arrays
+    container: usize,
+}
+
+impl<'doc, 'src> LazyValue<'doc, 'src> {
+    // the source text of this value, without the whitespace around it
+    pub fn raw(&self) -> &'src str {
+        &self.document.source[self.start..self.end]
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.raw().as_bytes()[0] {
+            b'{' => "object",
+            b'[' => "array",
+            b'"' => "string",
+            b'n' => "null",
+            b't' | b'f' => "boolean",
+            _ => "number",
+        }
+    }
+
+    // parse this subtree, it was validated with the document so it can't fail
+    pub fn to_value(&self) -> JsonValue {
+        parse(self.raw()).expect("validated by parse_lazy")
+    }
+
+    pub fn get(&self, key: &str) -> Option<LazyValue<'doc, 'src>> {
+        // the last of duplicate keys, as in `JsonValue`
+        self.entries().filter(|(k, _)| k == key).last().map(|(_, value)| value)
+    }
+
+    pub fn at(&self, index: usize) -> Option<LazyValue<'doc, 'src>> {
+        self.items().nth(index)
+    }
+
+    // array items, nothing for anything else
+    pub fn items(&self) -> impl Iterator<Item = LazyValue<'doc, 'src>> {
+        let children = match self.type_name() {
+            "array" => Some(self.children()),
+            _ => None,
+        };
+        children.into_iter().flatten().map(|(_, value)| value)
+    }
+
+    // object members in source order, duplicates included, nothing for anything else
+    pub fn entries(&self) -> impl Iterator<Item = (Cow<'src, str>, LazyValue<'doc, 'src>)> {
+        let children = match self.type_name() {
+            "object" => Some(self.children()),
+            _ => None,
+        };
+        children.into_iter().flatten().map(|(key, value)| (key.unwrap(), value))
+    }
+
+    // items or members, counted by going through them
+    pub fn len(&self) -> usize {
+        match self.type_name() {
+            "array" | "object" => self.children().count(),
+            _ => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // RFC 6901, see `JsonValue::pointer`
+    pub fn pointer(&self, pointer: &str) -> Option<LazyValue<'doc, 'src>> {
+        pointer_tokens(pointer)?.try_fold(*self, |value, token| match value.type_name() {
+            "object" => value.get(&token),
+            "array" => value.at(pointer_index(&token)?),
+            _ => None,
+        })
+    }
+
+    fn children(&self) -> Children<'doc, 'src> {
+        Children {
+            document: self.document,
+            object: self.type_name() == "object",
+            index: self.start + 1,
+            end: self.end - 1,
+            container: self.container + 1,
+        }
+    }
+}
+
+impl std::fmt::Debug for LazyValue<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LazyValue").field(&self.raw()).finish()
+    }
+}
+
+// One level of a container. Scalars are tokenized, containers are skipped by their
+// recorded end.
+struct Children<'doc, 'src> {
+    document: &'doc LazyDocument<'src>,
+    object: bool,
+    // where the next child (or the comma before it) starts, up to the closing bracket
+    index: usize,
+    end: usize,
+    // the next container at this level
+    container: usize,
+}
+
+impl<'doc, 'src> Iterator for Children<'doc, 'src> {
+    type Item = (Option<Cow<'src, str>>, LazyValue<'doc, 'src>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let source = &self.document.source[self.index..self.end];
+        let mut tokens = Tokenizer::with_scanner(source, self.document.scanner);
+        // validated already, so the only error is running into the closing bracket
+        let mut token = tokens.next().ok()?;
+        if token == Token::Comma {
+            token = tokens.next().ok()?;
+        }
+        let key = match token {
+            Token::String(key) if self.object => {
+                tokens.next().ok()?;
+                token = tokens.next().ok()?;
+                Some(key)
+            }
+            _ => None,
+        };
+
+        let start = self.index + tokens.token_start();
+        let value = match token {
+            Token::BracketOn | Token::BraceOn => {
+                let container = self.document.containers[self.container];
+                let value = LazyValue {
+                    document: self.document,
+                    start,
+                    end: container.end,
+                    container: self.container,
+                };
+                self.container = container.next;
+                value
+            }
+            _ => LazyValue {
+                document: self.document,
+                start,
+                end: self.index + tokens.index(),
+                container: 0,
+            },
+        };
+        self.index = value.end;
+        Some((key, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#" {
+        "meta": {"count": 2, "next": null},
+        "items": [ {"id": 1, "tags": ["a", "b"]} , {"id": 2, "tags": []}, -1.5e3 ],
+        "a\/b": "escé",
+        "meta": {"count": 3}
+    } "#;
+
+    #[test]
+    fn navigation() {
+        let document = parse_lazy(SOURCE).unwrap();
+        let root = document.root();
+        assert_eq!(root.to_value(), parse(SOURCE).unwrap());
+        assert_eq!(root.raw(), SOURCE.trim());
+        assert_eq!((root.type_name(), root.len()), ("object", 4));
+
+        let items = root.get("items").unwrap();
+        let raw = r#"[ {"id": 1, "tags": ["a", "b"]} , {"id": 2, "tags": []}, -1.5e3 ]"#;
+        assert_eq!(items.raw(), raw);
+        assert_eq!(items.at(1).unwrap().raw(), r#"{"id": 2, "tags": []}"#);
+        assert_eq!(items.at(2).unwrap().to_value(), JsonValue::from(-1500.0));
+        assert!(items.at(3).is_none() && items.get("id").is_none());
+        let tags: Vec<_> = items.items().filter_map(|item| Some(item.get("tags")?.len())).collect();
+        assert_eq!(tags, [2, 0]);
+
+        assert_eq!(root.pointer("/items/0/tags/1").unwrap().raw(), r#""b""#);
+        assert_eq!(root.pointer("/a~1b").unwrap().to_value(), JsonValue::from("escé"));
+        assert_eq!(root.pointer("/meta").unwrap().raw(), r#"{"count": 3}"#);
+        assert!(root.pointer("/items/01").is_none() && root.pointer("/items/+1").is_none());
+
+        let keys: Vec<_> = root.entries().map(|(k, _)| k.into_owned()).collect();
+        assert_eq!(keys, ["meta", "items", "a/b", "meta"]);
+
+        // text and keys are slices of the source, the document can go
+        let (raw, key) = {
+            let document = parse_lazy(SOURCE).unwrap();
+            let (key, value) = document.root().entries().next().unwrap();
+            (value.raw(), key)
+        };
+        assert_eq!((raw, &*key), (r#"{"count": 2, "next": null}"#, "meta"));
+    }
+
+    #[test]
+    fn validates_everything() {
+        let inputs = [r#"{"a": [1, 2}"#, r#"{"a": 1, "b": [tru]}"#, r#"[1, {"x" 2}]"#, "[", ""];
+        for input in inputs {
+            assert_eq!(parse_lazy(input).unwrap_err(), parse(input).unwrap_err(), "{}", input);
+        }
+        let document = parse_lazy("\"x\"").unwrap();
+        assert_eq!((document.root().type_name(), document.root().len()), ("string", 0));
+        assert!(parse_lazy("[[[]], {}]").unwrap().root().at(1).unwrap().is_empty());
+    }
+}
@@ -1,12 +1,51 @@
 mod parser;
 mod value;
+mod number;
 mod tokenizer;
+mod scan;
 mod error;
 mod macros;
 mod generator;
-
-use error::JsonError;
+mod convert;
+mod borrowed;
+mod position;
+mod recover;
+mod lines;
+mod stream;
+mod push;
+mod encoding;
+mod wtf8;
+mod canonical;
+mod diff;
+mod include;
+mod visit;
+mod flatten;
+mod merge;
+mod arena;
+mod lazy;
 
 pub type JsonResult<T> = Result<T, JsonError>;
-pub use parser::parse;
-pub use generator::stringify;
\ No newline at end of file
+pub use error::{Category, JsonError};
+pub use number::Number;
+pub use value::JsonValue;
+pub use parser::{parse, parse_complete, parse_with, parse_with_raw, ParseOptions, SurrogatePolicy};
+pub use borrowed::{parse_borrowed, JsonValueRef};
+pub use generator::stringify;
+pub use convert::{FromJson, ToJson};
+pub use position::Position;
+pub use recover::{parse_recovering, Diagnostic, Severity};
+pub use lines::{BadLine, JsonLinesReader, JsonLinesWriter};
+pub use stream::JsonStream;
+pub use push::{Event, PushParser, ValueBuilder};
+pub use encoding::{parse_bytes, Encoding};
+pub use wtf8::{Wtf8Chunk, Wtf8Chunks, Wtf8String};
+pub use canonical::to_canonical_string;
+pub use diff::{diff, diff_with, render_diff, ArrayMatch, Change, DiffOptions};
+pub use include::{any, approx, json_include, literal, Mismatch};
+#[cfg(feature = "regex")]
+pub use include::matching;
+pub use visit::{walk, walk_mut, Leaves, Order, Visitor, VisitorMut, Walk};
+pub use flatten::{flatten, unflatten, FlattenOptions, IndexStyle};
+pub use merge::{deep_merge, ArrayMerge, Conflict, MergeOptions, NullMerge};
+pub use arena::{parse_into_arena, Arena, ArenaValue};
+pub use lazy::{parse_lazy, LazyDocument, LazyValue};
@@ -0,0 +1,205 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    error::JsonError, generator::Generator, parser::parse_complete, value::JsonValue, JsonResult,
+};
+
+// what `JsonLinesReader` does with a line that is not valid json
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BadLine {
+    // yield the error, then stop
+    Abort,
+    // drop the line and keep reading, see `JsonLinesReader::skipped`
+    Skip,
+}
+
+// Newline delimited json: one value per line, blank lines are ignored.
+// Errors carry the line number as `JsonError::Line`.
+pub struct JsonLinesReader<R> {
+    reader: R,
+    policy: BadLine,
+    line: usize,
+    buffer: String,
+    skipped: Vec<usize>,
+    done: bool,
+}
+
+impl<R: BufRead> JsonLinesReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_policy(reader, BadLine::Abort)
+    }
+
+    pub fn with_policy(reader: R, policy: BadLine) -> Self {
+        JsonLinesReader {
+            reader,
+            policy,
+            line: 0,
+            buffer: String::new(),
+            skipped: Vec::new(),
+            done: false,
+        }
+    }
+
+    // line number of the last line read
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    // line numbers dropped under `BadLine::Skip`
+    pub fn skipped(&self) -> &[usize] {
+        &self.skipped
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for JsonLinesReader<R> {
+    type Item = JsonResult<JsonValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line += 1;
+                    let text = self.buffer.trim_end_matches(['\n', '\r']);
+                    if text.trim().is_empty() {
+                        continue;
+                    }
+                    match parse_complete(text) {
+                        Ok(value) => return Some(Ok(value)),
+                        Err(_) if self.policy == BadLine::Skip => self.skipped.push(self.line),
+                        Err(err) => {
+                            self.done = true;
+                            return Some(Err(err.at_line(self.line)));
+                        }
+                    }
+                }
+                // a line that is not utf-8 has been consumed, it is just another bad line
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                    self.line += 1;
+                    if self.policy == BadLine::Skip {
+                        self.skipped.push(self.line);
+                        continue;
+                    }
+                    self.done = true;
+                    return Some(Err(JsonError::from(err).at_line(self.line)));
+                }
+                // reading can't be resumed after an i/o error, whatever the policy
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(JsonError::from(err).at_line(self.line + 1)));
+                }
+            }
+        }
+        None
+    }
+}
+
+// Writes one minified value per line. The minified generator emits no whitespace
+// and escapes newlines inside strings, but a `JsonValue::Raw` goes out as it was
+// written, so a line that comes out with a line break has its whitespace outside
+// strings taken out. Every record stays on its own line.
+pub struct JsonLinesWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesWriter { writer }
+    }
+
+    pub fn write(&mut self, value: &JsonValue) -> JsonResult<()> {
+        let mut gen = Generator::new(true, 0);
+        gen.write_json(value);
+        let mut line = gen.value();
+        if line.contains(['\n', '\r']) {
+            line = minify(&line);
+        }
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> JsonResult<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+// `json` without whitespace outside its strings
+fn minify(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    let (mut string, mut escaped) = (false, false);
+    for ch in json.chars() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if string => escaped = true,
+            '"' => string = !string,
+            ' ' | '\t' | '\n' | '\r' if !string => continue,
+            _ => (),
+        }
+        out.push(ch);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, object};
+
+    const LOG: &str = "{\"level\": \"info\"}\r\n\n[1, 2]\n{\"level\": \n\"x\" 1\n3";
+
+    #[test]
+    fn abort_on_bad_line() {
+        let mut reader = JsonLinesReader::new(LOG.as_bytes());
+        assert_eq!(reader.next(), Some(Ok(object! {"level" => "info"})));
+        assert_eq!(reader.next(), Some(Ok(array![1, 2])));
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err, JsonError::UnexpectedEndOfJson.at_line(4));
+        assert_eq!(err.to_string(), "line 4: unexpected end of json");
+        assert!(err.is_eof());
+        assert_eq!(reader.next(), None);
+    }
+
+    #[test]
+    fn skip_bad_lines() {
+        let mut reader = JsonLinesReader::with_policy(LOG.as_bytes(), BadLine::Skip);
+        let values: Vec<_> = reader.by_ref().map(Result::unwrap).collect();
+        assert_eq!(values, vec![object! {"level" => "info"}, array![1, 2], 3.into()]);
+        assert_eq!(reader.skipped(), &[4, 5]);
+        assert_eq!(reader.line(), 6);
+
+        let bytes: &[u8] = b"1\n\"\xff\"\n2\n";
+        let mut reader = JsonLinesReader::with_policy(bytes, BadLine::Skip);
+        assert_eq!(reader.by_ref().count(), 2);
+        assert_eq!(reader.skipped(), &[2]);
+    }
+
+    #[test]
+    fn writer_keeps_one_value_per_line() {
+        let mut writer = JsonLinesWriter::new(Vec::new());
+        writer.write(&object! {"msg" => "two\nlines", "n" => 1}).unwrap();
+        writer.write(&array![]).unwrap();
+        let out = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(out, "{\"msg\":\"two\\nlines\",\"n\":1}\n[]\n");
+
+        let values: Vec<_> = JsonLinesReader::new(out.as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(values[0], object! {"msg" => "two\nlines", "n" => 1});
+
+        // raw fragments lose their line breaks, and only whitespace outside strings
+        let raw = JsonValue::Raw("{\"a b\": [1,\n 2],\r\n \"c\": \"\\\" \"}".to_string());
+        let mut writer = JsonLinesWriter::new(Vec::new());
+        writer.write(&array![raw, "x  y"]).unwrap();
+        let out = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(out, "[{\"a b\":[1,2],\"c\":\"\\\" \"},\"x  y\"]\n");
+    }
+}
@@ -1,21 +1,17 @@
 #[macro_export]
 macro_rules! array {
-  [] => ($crate::value::JsonValue::Array(Vec::new()));
+  [] => ($crate::JsonValue::Array(Vec::new()));
 
   [ $($item:expr),*]  => ({
-    let mut arr = Vec::new();
+    let arr = vec![$($item.into()),*];
 
-    $(
-      arr.push($item.into());
-    )*
-
-    $crate::value::JsonValue::Array(arr)
+    $crate::JsonValue::Array(arr)
   });
 }
 
 #[macro_export]
 macro_rules! object {
-    {} => ($crate::value::JsonValue::Object(std::collections::BTreeMap::new()));
+    {} => ($crate::JsonValue::Object(std::collections::BTreeMap::new()));
 
     { $($key:expr => $val:expr), * } => ({
       use std::collections::BTreeMap;
@@ -26,22 +22,295 @@ macro_rules! object {
         obj.insert($key.into(), $val.into());
       )*
 
-      $crate::value::JsonValue::Object(obj)
+      $crate::JsonValue::Object(obj)
 
     });
 }
 
+// JSON literal syntax with rust expressions in value position:
+//
+//     json!({"id": id, "tags": ["a", null, true], "nested": {"b": -1.5},})
+//
+// Keys are string literals or any expression convertible into `String`, computed ones
+// in parentheses: `json!({(name): 1})`. Values are converted with `JsonValue::from`.
+// An unexpected token is reported by `json_unexpected!`, which has no rule taking any
+// token, so rustc points at that token.
+#[macro_export]
+macro_rules! json {
+    ($($json:tt)+) => {
+        $crate::json_internal!($($json)+)
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! json_internal {
+    // arrays: (@array [parsed elements,] rest of the tokens)
+    (@array [$($elems:expr,)*]) => {
+        vec![$($elems,)*]
+    };
+
+    (@array [$($elems:expr),*]) => {
+        vec![$($elems),*]
+    };
+
+    (@array [$($elems:expr,)*] null $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!(null)] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] true $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!(true)] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] false $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!(false)] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!([$($array)*])] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] {$($map:tt)*} $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!({$($map)*})] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!($next),] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] $last:expr) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!($last)])
+    };
+
+    // the comma after an element
+    (@array [$($elems:expr),*] , $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)*] $($rest)*)
+    };
+
+    (@array [$($elems:expr),*] $unexpected:tt $($rest:tt)*) => {
+        $crate::json_unexpected!($unexpected)
+    };
+
+    // objects: (@object map (key tokens so far) (rest of the tokens) (copy of the rest)),
+    // the copy is there to report the first token of the rest when it is wrong. Once
+    // the value is parsed the key is put in brackets: (@object map [key] (value) rest)
+    (@object $object:ident () () ()) => {};
+
+    (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
+        let _ = $object.insert(($($key)+).into(), $value);
+        $crate::json_internal!(@object $object () ($($rest)*) ($($rest)*));
+    };
+
+    (@object $object:ident [$($key:tt)+] ($value:expr) $unexpected:tt $($rest:tt)*) => {
+        $crate::json_unexpected!($unexpected);
+    };
+
+    (@object $object:ident [$($key:tt)+] ($value:expr)) => {
+        let _ = $object.insert(($($key)+).into(), $value);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: null $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!(null)) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: true $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!(true)) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: false $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!(false)) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: [$($array:tt)*] $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!([$($array)*])) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: {$($map:tt)*} $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!({$($map)*})) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: $value:expr , $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!($value)) , $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: $value:expr) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!($value)));
+    };
+
+    // a key without a value, "unexpected end of macro invocation"
+    (@object $object:ident ($($key:tt)+) (:) $copy:tt) => {
+        $crate::json_internal!();
+    };
+
+    (@object $object:ident ($($key:tt)+) () $copy:tt) => {
+        $crate::json_internal!();
+    };
+
+    // a colon without a key
+    (@object $object:ident () (: $($rest:tt)*) ($colon:tt $($copy:tt)*)) => {
+        $crate::json_unexpected!($colon);
+    };
+
+    // a comma before the colon
+    (@object $object:ident ($($key:tt)*) (, $($rest:tt)*) ($comma:tt $($copy:tt)*)) => {
+        $crate::json_unexpected!($comma);
+    };
+
+    // a computed key
+    (@object $object:ident () (($key:expr) : $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object ($key) (: $($rest)*) (: $($rest)*));
+    };
+
+    // whatever follows the colon is not a value
+    (@object $object:ident ($($key:tt)*) (: $($unexpected:tt)+) $copy:tt) => {
+        $crate::json_expect_expr_comma!($($unexpected)+);
+    };
+
+    // a literal key is complete, so anything but the colon after it is wrong
+    (@object $object:ident ($key:literal) ($unexpected:tt $($rest:tt)*) $copy:tt) => {
+        $crate::json_unexpected!($unexpected);
+    };
+
+    // one more token of the key
+    (@object $object:ident ($($key:tt)*) ($tt:tt $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object ($($key)* $tt) ($($rest)*) ($($rest)*));
+    };
+
+    (null) => {
+        $crate::JsonValue::Null
+    };
+
+    (true) => {
+        $crate::JsonValue::Boolen(true)
+    };
+
+    (false) => {
+        $crate::JsonValue::Boolen(false)
+    };
+
+    ([]) => {
+        $crate::JsonValue::Array(vec![])
+    };
+
+    ([ $($tt:tt)+ ]) => {
+        $crate::JsonValue::Array($crate::json_internal!(@array [] $($tt)+))
+    };
+
+    ({}) => {
+        $crate::JsonValue::Object(::std::collections::BTreeMap::new())
+    };
+
+    ({ $($tt:tt)+ }) => {
+        $crate::JsonValue::Object({
+            let mut object = ::std::collections::BTreeMap::new();
+            $crate::json_internal!(@object object () ($($tt)+) ($($tt)+));
+            object
+        })
+    };
+
+    ($other:expr) => {
+        $crate::JsonValue::from($other)
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! json_unexpected {
+    () => {};
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! json_expect_expr_comma {
+    ($e:expr , $($tt:tt)*) => {};
+}
+
+// `assert_eq!` for json values that prints what differs instead of two `Debug` dumps.
+// Numbers compare by value, see `diff`.
+#[macro_export]
+macro_rules! assert_json_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert_json_eq!($left, $right, "json values differ")
+    };
+
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                let changes = $crate::diff(left, right);
+                if !changes.is_empty() {
+                    panic!(
+                        "assertion `left == right` failed: {}\n{}",
+                        format_args!($($arg)+),
+                        $crate::render_diff(&changes, false)
+                    );
+                }
+            }
+        }
+    };
+}
+
+// Passes when `expected` is contained in `actual`, see `json_include`. Placeholders
+// from `any()`, `matching(..)` (with the `regex` feature) and `approx(..)` can stand
+// in for expected values, `literal(..)` keeps an object with their keys as it is.
+#[macro_export]
+macro_rules! assert_json_include {
+    (actual: $actual:expr, expected: $expected:expr $(,)?) => {
+        match (&$actual, &$expected) {
+            (actual, expected) => {
+                let mismatches = $crate::json_include(actual, expected);
+                if !mismatches.is_empty() {
+                    let lines: Vec<String> = mismatches.iter().map(|m| m.to_string()).collect();
+                    panic!("json does not include the expected value:\n{}", lines.join("\n"));
+                }
+            }
+        }
+    };
+
+    (expected: $expected:expr, actual: $actual:expr $(,)?) => {
+        $crate::assert_json_include!(actual: $actual, expected: $expected)
+    };
+}
+
 #[cfg(test)]
 mod tests {
-    #[cfg(test)]
-    mod tests {
-        #[test]
-        fn macro_test() {
-            let arr = array![1, 2, "3"];
-            let obj = object! {"name"=>"abc", "age" => 123};
-
-            println!("arr {:?}", arr);
-            println!("obj {:?}", obj);
-        }
+    // the `.stderr` files next to the cases pin the span of each error to the token
+    #[test]
+    fn json_errors_point_at_the_token() {
+        trybuild::TestCases::new().compile_fail("tests/ui/json_*.rs");
+    }
+
+    #[test]
+    fn macro_test() {
+        let arr = array![1, 2, "3"];
+        let obj = object! {"name"=>"abc", "age" => 123};
+
+        println!("arr {:?}", arr);
+        println!("obj {:?}", obj);
+    }
+
+    #[test]
+    fn json_literals() {
+        use crate::{parse, JsonValue};
+
+        let x = 7;
+        let key = String::from("computed");
+        let value = json!({
+            "a": [1, null, true, {"b": x}],
+            "c": -1.5,
+            "d": [[], {}, false,],
+            (key): x * 2,
+            "e": "s".repeat(2),
+            "f": JsonValue::Null,
+        });
+        let expected = parse(
+            r#"{"a": [1, null, true, {"b": 7}], "c": -1.5, "d": [[], {}, false],
+                "computed": 14, "e": "ss", "f": null}"#,
+        );
+        assert_eq!(Ok(value), expected);
+
+        assert_eq!(json!(null), JsonValue::Null);
+        assert_eq!(json!([1, "2", [3]]), array![1, "2", array![3]]);
+        assert_eq!(json!({"k": {"nested": x}}), object! {"k" => object! {"nested" => 7}});
+        assert_eq!(json!(Some(1)), JsonValue::from(1));
     }
 }
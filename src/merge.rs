@@ -0,0 +1,303 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::value::{escape_pointer, JsonValue};
+
+// what an array in the overlay does to the array under it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ArrayMerge {
+    // the overlay array takes its place
+    #[default]
+    Replace,
+    // overlay items after the base ones
+    Concat,
+    // overlay items that aren't already there (by `semantic_eq`) after the base ones
+    Union,
+    // an overlay object whose value for this key matches a base item's is merged into
+    // it, other items are added at the end
+    Key(String),
+}
+
+// what a null in the overlay does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NullMerge {
+    // the value becomes null
+    #[default]
+    Overwrite,
+    // the key is removed from its object. Anywhere else it is still a null.
+    Delete,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    pub arrays: ArrayMerge,
+    pub nulls: NullMerge,
+}
+
+// a value replaced by one of another type, nulls aside
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    // RFC 6901 pointer into the merged value
+    pub path: String,
+    pub base: &'static str,
+    pub overlay: &'static str,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} replaced by {}", self.path, self.base, self.overlay)
+    }
+}
+
+// Merge `overlay` into `base`, objects key by key and everything else replaced by the
+// overlay or as `options` say for arrays and nulls. The overlay always wins, type
+// changes on the way are returned so the caller can warn or refuse.
+//
+//     deep_merge(&mut config, environment, MergeOptions::default());
+//     deep_merge(&mut config, user, MergeOptions::default());
+pub fn deep_merge(
+    base: &mut JsonValue,
+    overlay: JsonValue,
+    options: MergeOptions,
+) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    merge(&mut String::new(), base, overlay, &options, &mut conflicts);
+    conflicts
+}
+
+fn merge(
+    path: &mut String,
+    base: &mut JsonValue,
+    overlay: JsonValue,
+    options: &MergeOptions,
+    conflicts: &mut Vec<Conflict>,
+) {
+    let len = path.len();
+    match (base, overlay) {
+        (JsonValue::Object(base), JsonValue::Object(overlay)) => {
+            for (key, value) in overlay {
+                if value == JsonValue::Null && options.nulls == NullMerge::Delete {
+                    base.remove(&key);
+                    continue;
+                }
+                match base.get_mut(&key) {
+                    Some(existing) => {
+                        path.push('/');
+                        path.push_str(&escape_pointer(&key));
+                        merge(path, existing, value, options, conflicts);
+                        path.truncate(len);
+                    }
+                    None => {
+                        base.insert(key, strip_nulls(value, options));
+                    }
+                }
+            }
+        }
+        (JsonValue::Array(base), JsonValue::Array(overlay)) => {
+            let overlay = overlay.into_iter().map(|item| strip_nulls(item, options));
+            match &options.arrays {
+                ArrayMerge::Replace => *base = overlay.collect(),
+                ArrayMerge::Concat => base.extend(overlay),
+                ArrayMerge::Union => {
+                    for item in overlay {
+                        if !base.iter().any(|existing| existing.semantic_eq(&item)) {
+                            base.push(item);
+                        }
+                    }
+                }
+                ArrayMerge::Key(key) => {
+                    // base items by the hash of their id, added ones included.
+                    // `content_hash` agrees with `semantic_eq`, a bucket can still hold
+                    // unequal ids that collide.
+                    let mut index: HashMap<u64, Vec<usize>> = HashMap::new();
+                    for (i, existing) in base.iter().enumerate() {
+                        if let Some(hash) = id_hash(existing, key) {
+                            index.entry(hash).or_default().push(i);
+                        }
+                    }
+                    for item in overlay {
+                        let hash = id_hash(&item, key);
+                        let found = hash.and_then(|hash| index.get(&hash)).and_then(|bucket| {
+                            let item_id = id(&item, key)?;
+                            bucket.iter().copied().find(|&i| {
+                                id(&base[i], key).is_some_and(|id| id.semantic_eq(item_id))
+                            })
+                        });
+                        match found {
+                            Some(i) => {
+                                path.push('/');
+                                path.push_str(&i.to_string());
+                                merge(path, &mut base[i], item, options, conflicts);
+                                path.truncate(len);
+                            }
+                            None => {
+                                if let Some(hash) = hash {
+                                    index.entry(hash).or_default().push(base.len());
+                                }
+                                base.push(item);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        (base, overlay) => {
+            let typed = *base != JsonValue::Null && overlay != JsonValue::Null;
+            if typed && base.type_name() != overlay.type_name() {
+                conflicts.push(Conflict {
+                    path: path.clone(),
+                    base: base.type_name(),
+                    overlay: overlay.type_name(),
+                });
+            }
+            *base = strip_nulls(overlay, options);
+        }
+    }
+}
+
+// the value an `ArrayMerge::Key` item is matched by
+fn id<'v>(value: &'v JsonValue, key: &str) -> Option<&'v JsonValue> {
+    match value {
+        JsonValue::Object(object) => object.get(key),
+        _ => None,
+    }
+}
+
+fn id_hash(value: &JsonValue, key: &str) -> Option<u64> {
+    id(value, key)?.content_hash().ok()
+}
+
+// An overlay value that goes in without a base to merge with. Under `NullMerge::Delete`
+// its objects lose their null members, as if merged into empty ones.
+fn strip_nulls(value: JsonValue, options: &MergeOptions) -> JsonValue {
+    if options.nulls != NullMerge::Delete {
+        return value;
+    }
+    match value {
+        JsonValue::Object(object) => JsonValue::Object(
+            object
+                .into_iter()
+                .filter(|(_, value)| *value != JsonValue::Null)
+                .map(|(key, value)| (key, strip_nulls(value, options)))
+                .collect(),
+        ),
+        JsonValue::Array(items) => {
+            JsonValue::Array(items.into_iter().map(|item| strip_nulls(item, options)).collect())
+        }
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn layers() {
+        let mut config = json!({
+            "server": {"host": "localhost", "port": 8080, "tls": {"cert": "a.pem"}},
+            "features": ["login"],
+            "debug": false
+        });
+        let environment = json!({
+            "server": {"host": "0.0.0.0", "tls": null},
+            "features": ["search"]
+        });
+        let user = json!({"debug": true, "server": {"port": "9000"}, "theme": {"dark": true}});
+
+        assert!(deep_merge(&mut config, environment, MergeOptions::default()).is_empty());
+        let conflicts = deep_merge(&mut config, user, MergeOptions::default());
+        assert_eq!(
+            config,
+            json!({
+                "server": {"host": "0.0.0.0", "port": "9000", "tls": null},
+                "features": ["search"],
+                "debug": true,
+                "theme": {"dark": true}
+            })
+        );
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].to_string(), "/server/port: number replaced by string");
+
+        let mut root = json!([1]);
+        let conflicts = deep_merge(&mut root, json!({"a": 1}), MergeOptions::default());
+        assert_eq!((root, conflicts[0].path.as_str()), (json!({"a": 1}), ""));
+    }
+
+    #[test]
+    fn null_policies() {
+        let delete = MergeOptions {
+            nulls: NullMerge::Delete,
+            ..Default::default()
+        };
+        let mut value = json!({"a": 1, "b": {"c": 2, "d": 3}});
+        let overlay = json!({"a": null, "b": {"c": null}, "x": null, "y": {"z": null}});
+        deep_merge(&mut value, overlay, delete.clone());
+        assert_eq!(value, json!({"b": {"d": 3}, "y": {}}));
+
+        // objects that replace a value or are added to an array lose their nulls too
+        let mut value = json!({"a": 1, "b": [{"id": 1}], "c": [1]});
+        let overlay = json!({
+            "a": {"x": null, "y": [null, {"z": null}]},
+            "b": [{"id": 2, "w": null}],
+            "c": [{"v": null}]
+        });
+        let options = MergeOptions {
+            arrays: ArrayMerge::Key("id".to_string()),
+            ..delete.clone()
+        };
+        deep_merge(&mut value, overlay, options);
+        let expected = json!({"a": {"y": [null, {}]}, "b": [{"id": 1}, {"id": 2}], "c": [1, {}]});
+        assert_eq!(value, expected);
+        for arrays in [ArrayMerge::Replace, ArrayMerge::Concat, ArrayMerge::Union] {
+            let mut value = json!({"c": [1]});
+            let options = MergeOptions { arrays, ..delete.clone() };
+            deep_merge(&mut value, json!({"c": [{"v": null}]}), options);
+            assert!(!value.dump().contains("null"), "{}", value.dump());
+        }
+
+        let mut value = json!({"a": 1});
+        deep_merge(&mut value, json!({"a": null, "x": null}), MergeOptions::default());
+        assert_eq!(value, json!({"a": null, "x": null}));
+    }
+
+    #[test]
+    fn array_strategies() {
+        let merged = |arrays: ArrayMerge, base: JsonValue, overlay: JsonValue| {
+            let mut base = base;
+            let options = MergeOptions {
+                arrays,
+                ..Default::default()
+            };
+            let conflicts = deep_merge(&mut base, overlay, options);
+            (base, conflicts)
+        };
+
+        assert_eq!(merged(ArrayMerge::Replace, json!([1, 2]), json!([3])).0, json!([3]));
+        assert_eq!(merged(ArrayMerge::Concat, json!([1, 2]), json!([2, 3])).0, json!([1, 2, 2, 3]));
+        let union = merged(ArrayMerge::Union, json!([1, 2]), json!([2.0, 3, 3]));
+        assert_eq!(union.0, json!([1, 2, 3]));
+
+        let (value, conflicts) = merged(
+            ArrayMerge::Key("name".to_string()),
+            json!({"users": [{"name": "ada", "role": "admin", "age": 36}, {"name": "bob"}]}),
+            json!({"users": [{"name": "cy"}, {"name": "ada", "role": ["dev"]}, "x"]}),
+        );
+        assert_eq!(
+            value,
+            json!({"users": [
+                {"name": "ada", "role": ["dev"], "age": 36},
+                {"name": "bob"},
+                {"name": "cy"},
+                "x"
+            ]})
+        );
+        assert_eq!(conflicts[0].to_string(), "/users/0/role: string replaced by array");
+
+        // ids compare as numbers, and an added item is matched by later ones
+        let by_id = || ArrayMerge::Key("id".to_string());
+        let overlay = json!([{"id": 1.0, "a": 1}, {"id": 2, "a": 2}, {"id": 2, "b": 3}]);
+        let (value, _) = merged(by_id(), json!([{"id": 1}]), overlay);
+        assert_eq!(value, json!([{"id": 1.0, "a": 1}, {"id": 2, "a": 2, "b": 3}]));
+    }
+}
@@ -0,0 +1,274 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+// Integers are kept exactly; anything with a fraction or an exponent is a float.
+// 42                   -> Integer
+// 18446744073709551615 -> Unsigned
+// 1.5e3                -> Float
+//
+// An integer is stored in the first of `Integer`, `Unsigned` and `BigInteger` it fits
+// in, which keeps a number at 24 bytes where a plain i128 would make it 32. Build them
+// with `Number::from` to get that; the integer variants compare and hash by value
+// either way.
+//
+// Equality, ordering and hashing are structural so that numbers can be map keys:
+// floats compare by `f64::total_cmp`, so NaN equals itself and -0.0 sorts before
+// (and is not equal to) 0.0, and `Integer(1)` is not equal to `Float(1.0)`.
+// Otherwise numbers are ordered by value, an integer right before the float of the
+// same value. `JsonValue::semantic_eq` compares by value only.
+#[derive(Debug, Clone, Copy)]
+pub enum Number {
+    Integer(i64),
+    // above i64::MAX
+    Unsigned(u64),
+    // the high and low halves of an i128 outside both ranges above
+    BigInteger(i64, u64),
+    Float(f64),
+}
+
+impl Number {
+    pub fn is_integer(&self) -> bool {
+        !matches!(self, Number::Float(_))
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match (*self, self.as_i128()) {
+            (Number::Float(n), _) => n,
+            (_, Some(n)) => n as f64,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        i64::try_from(self.as_i128()?).ok()
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        u64::try_from(self.as_i128()?).ok()
+    }
+
+    pub fn as_i128(&self) -> Option<i128> {
+        match *self {
+            Number::Integer(n) => Some(n as i128),
+            Number::Unsigned(n) => Some(n as i128),
+            Number::BigInteger(high, low) => Some((high as i128) << 64 | low as i128),
+            Number::Float(_) => None,
+        }
+    }
+
+    // equal by value whatever the representation, NaN equals nothing
+    pub(crate) fn value_eq(&self, other: &Number) -> bool {
+        match (*self, *other) {
+            (Number::Float(a), Number::Float(b)) => a == b,
+            (Number::Float(f), n) | (n, Number::Float(f)) => {
+                !f.is_nan() && cmp_int_float(n.as_i128().unwrap(), f) == Ordering::Equal
+            }
+            (a, b) => a.as_i128() == b.as_i128(),
+        }
+    }
+}
+
+// exact, without going through `n as f64`. NaN sorts where `total_cmp` puts it,
+// past the infinity of its sign
+fn cmp_int_float(n: i128, f: f64) -> Ordering {
+    const LIMIT: f64 = 170141183460469231731687303715884105728.0; // 2^127
+    if f.is_nan() {
+        return match f.is_sign_negative() {
+            true => Ordering::Greater,
+            false => Ordering::Less,
+        };
+    }
+    if f >= LIMIT {
+        return Ordering::Less;
+    }
+    if f < -LIMIT {
+        return Ordering::Greater;
+    }
+    let whole = f.trunc();
+    n.cmp(&(whole as i128)).then(whole.partial_cmp(&f).unwrap())
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (*self, *other) {
+            (Number::Float(a), Number::Float(b)) => a.total_cmp(&b),
+            (n, Number::Float(f)) => cmp_int_float(n.as_i128().unwrap(), f).then(Ordering::Less),
+            (Number::Float(f), n) => cmp_int_float(n.as_i128().unwrap(), f).reverse().then(Ordering::Greater),
+            (a, b) => a.as_i128().cmp(&b.as_i128()),
+        }
+    }
+}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match (*self, self.as_i128()) {
+            (Number::Float(f), _) => {
+                state.write_u8(1);
+                f.to_bits().hash(state);
+            }
+            (_, n) => {
+                state.write_u8(0);
+                n.hash(state);
+            }
+        }
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (*self, self.as_i128()) {
+            (Number::Float(ref value), _) => value.fmt(f),
+            (_, Some(ref value)) => value.fmt(f),
+            _ => unreachable!(),
+        }
+    }
+}
+
+macro_rules! impl_from_int_for_number {
+    ($($t: ident), *) => {
+      $(
+        impl From<$t> for Number {
+            fn from(value: $t) -> Number {
+                Number::from(value as i128)
+            }
+        }
+      )*
+    };
+}
+
+impl_from_int_for_number!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl From<i128> for Number {
+    fn from(value: i128) -> Number {
+        if let Ok(n) = i64::try_from(value) {
+            return Number::Integer(n);
+        }
+        if let Ok(n) = u64::try_from(value) {
+            return Number::Unsigned(n);
+        }
+        Number::BigInteger((value >> 64) as i64, value as u64)
+    }
+}
+
+impl From<u128> for Number {
+    fn from(value: u128) -> Number {
+        match i128::try_from(value) {
+            Ok(n) => Number::from(n),
+            Err(_) => Number::Float(value as f64),
+        }
+    }
+}
+
+impl From<f32> for Number {
+    fn from(value: f32) -> Number {
+        Number::Float(value as f64)
+    }
+}
+
+impl From<f64> for Number {
+    fn from(value: f64) -> Number {
+        Number::Float(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossless_integers() {
+        assert_eq!(Number::from(u64::MAX).as_u64(), Some(u64::MAX));
+        assert_eq!(Number::from(i128::MIN).as_i128(), Some(i128::MIN));
+        assert_eq!(Number::from(u64::MAX).to_string(), "18446744073709551615");
+        assert_eq!(Number::from(u128::MAX), Number::Float(u128::MAX as f64));
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(Number::from(1.5f32), Number::Float(1.5));
+        assert_eq!(Number::from(-0.25).as_f64(), -0.25);
+        assert_eq!(Number::from(3.0).as_i64(), None);
+        assert!(!Number::from(3.0).is_integer());
+    }
+
+    #[test]
+    fn total_order() {
+        let nan = Number::Float(f64::NAN);
+        assert_eq!(nan, nan);
+        assert_ne!(Number::Float(-0.0), Number::Float(0.0));
+        assert_ne!(Number::Integer(1), Number::Float(1.0));
+
+        let mut numbers = vec![
+            Number::Float(f64::INFINITY),
+            nan,
+            Number::Float(1.5),
+            Number::Float(1.0),
+            Number::Integer(1),
+            Number::from(i128::MAX),
+            Number::Float(-f64::NAN),
+            Number::Float(-0.0),
+            Number::Integer(0),
+            Number::Integer(-2),
+        ];
+        numbers.sort();
+        assert_eq!(
+            numbers,
+            vec![
+                Number::Float(-f64::NAN),
+                Number::Integer(-2),
+                Number::Integer(0),
+                Number::Float(-0.0),
+                Number::Integer(1),
+                Number::Float(1.0),
+                Number::Float(1.5),
+                Number::from(i128::MAX),
+                Number::Float(f64::INFINITY),
+                nan,
+            ]
+        );
+
+        assert!(Number::Integer(1).value_eq(&Number::Float(1.0)));
+        assert!(Number::Float(-0.0).value_eq(&Number::Integer(0)));
+        assert!(!Number::Integer(9007199254740993).value_eq(&Number::Float(9007199254740992.0)));
+        assert!(!nan.value_eq(&nan));
+    }
+
+    #[test]
+    fn integer_representations() {
+        assert_eq!(std::mem::size_of::<Number>(), 24);
+        assert_eq!(Number::from(-1), Number::Integer(-1));
+        assert_eq!(Number::from(u64::MAX), Number::Unsigned(u64::MAX));
+        assert_eq!(Number::from(i128::MIN), Number::BigInteger(i64::MIN, 0));
+        for n in [0, -1, i64::MAX as i128 + 1, u64::MAX as i128 + 1, i128::MIN, i128::MAX] {
+            assert_eq!(Number::from(n).as_i128(), Some(n));
+            assert_eq!(Number::from(n).to_string(), n.to_string());
+        }
+
+        // unnormalized values are still equal by value
+        assert_eq!(Number::Unsigned(1), Number::Integer(1));
+        assert_eq!(Number::BigInteger(0, 5).cmp(&Number::Integer(6)), Ordering::Less);
+        let hash = |n: Number| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            n.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(Number::Unsigned(7)), hash(Number::Integer(7)));
+    }
+}
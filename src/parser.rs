@@ -1,112 +1,299 @@
-use std::collections::BTreeMap;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+};
 
 use crate::{
     error::JsonError,
     tokenizer::{Token, Tokenizer},
-    value::JsonValue,
+    value::{pointer_index, pointer_tokens, JsonValue},
     JsonResult,
 };
 
-struct Parser<'a> {
+// what to do with a `\uXXXX` surrogate escape that has no other half
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SurrogatePolicy {
+    // fail with `JsonError::UnpairedSurrogate`
+    #[default]
+    Error,
+    // decode it as U+FFFD
+    Replace,
+    // keep it, the string becomes a `JsonValue::Wtf8`. Keys are always `String`
+    // and get U+FFFD instead.
+    Preserve,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    pub surrogates: SurrogatePolicy,
+}
+
+// The pointers given to `parse_with_raw` as a trie of their reference tokens, which
+// the parser walks down with the keys and indexes it reads. Node 0 is the root.
+struct RawPointers {
+    nodes: Vec<RawNode>,
+}
+
+#[derive(Default)]
+struct RawNode {
+    // a pointer ends here
+    raw: bool,
+    keys: HashMap<String, usize>,
+    // the tokens that are also array indexes
+    indexes: HashMap<usize, usize>,
+}
+
+impl RawPointers {
+    // pointers that aren't RFC 6901 pointers name nothing and are left out
+    fn new(pointers: &[&str]) -> Self {
+        let mut nodes = vec![RawNode::default()];
+        for pointer in pointers {
+            let Some(tokens) = pointer_tokens(pointer) else {
+                continue;
+            };
+            let mut at = 0;
+            for token in tokens {
+                at = match nodes[at].keys.get(&token) {
+                    Some(&next) => next,
+                    None => {
+                        let next = nodes.len();
+                        nodes.push(RawNode::default());
+                        if let Some(index) = pointer_index(&token) {
+                            nodes[at].indexes.insert(index, next);
+                        }
+                        nodes[at].keys.insert(token, next);
+                        next
+                    }
+                };
+            }
+            nodes[at].raw = true;
+        }
+        RawPointers { nodes }
+    }
+}
+
+// What the grammar builds. `Parser` is generic over it so `JsonValue` and
+// `JsonValueRef` come out of the same grammar.
+pub(crate) trait Build<'a>: Sized {
+    type Key: Ord;
+
+    // a null, boolean, number or string token, the token back for anything else
+    fn scalar(token: Token<'a>) -> Result<Self, Token<'a>>;
+    fn key(key: Cow<'a, str>) -> Self::Key;
+    fn array(items: Vec<Self>) -> Self;
+    fn object(members: BTreeMap<Self::Key, Self>) -> Self;
+}
+
+impl<'a> Build<'a> for JsonValue {
+    type Key = String;
+
+    fn scalar(token: Token<'a>) -> Result<Self, Token<'a>> {
+        Ok(match token {
+            Token::Null => JsonValue::Null,
+            Token::Boolen(b) => JsonValue::Boolen(b),
+            Token::Number(n) => JsonValue::Number(n),
+            Token::String(s) => JsonValue::String(s.into_owned()),
+            Token::Wtf8(s) => JsonValue::Wtf8(s),
+            token => return Err(token),
+        })
+    }
+
+    fn key(key: Cow<'a, str>) -> String {
+        key.into_owned()
+    }
+
+    fn array(items: Vec<Self>) -> Self {
+        JsonValue::Array(items)
+    }
+
+    fn object(members: BTreeMap<String, Self>) -> Self {
+        JsonValue::Object(members)
+    }
+}
+
+pub(crate) struct Parser<'a, V = JsonValue> {
+    source: &'a str,
     tokenizer: Tokenizer<'a>,
+    raw: RawPointers,
+    // the trie node of the value being parsed, `None` when no pointer goes through it
+    at: Option<usize>,
+    // builds a value the pointers name from its source text, only `parse_with_raw` sets it
+    capture: Option<fn(&'a str) -> V>,
+    value: PhantomData<V>,
 }
 
-// parse_value: str -> match Token -> JsonValue
 impl<'a> Parser<'a> {
     pub fn new(source: &'a str) -> Self {
+        Parser::with_tokenizer(source, Tokenizer::new(source))
+    }
+
+    pub fn with_options(source: &'a str, options: ParseOptions) -> Self {
+        Parser::with_tokenizer(source, Tokenizer::with_surrogates(source, options.surrogates))
+    }
+}
+
+// parse_value: str -> match Token -> V
+impl<'a, V: Build<'a>> Parser<'a, V> {
+    pub(crate) fn with_tokenizer(source: &'a str, tokenizer: Tokenizer<'a>) -> Self {
         Parser {
-            tokenizer: Tokenizer::new(source),
+            source,
+            tokenizer,
+            raw: RawPointers { nodes: Vec::new() },
+            at: None,
+            capture: None,
+            value: PhantomData,
         }
     }
 
+    // byte offset just past the last token
+    pub(crate) fn offset(&self) -> usize {
+        self.tokenizer.index()
+    }
+
     // str -> Token
-    fn consume(&mut self) -> JsonResult<Token> {
+    pub(crate) fn consume(&mut self) -> JsonResult<Token<'a>> {
         self.tokenizer.next()
     }
 
-    fn parse_value(&mut self, token: Token) -> JsonResult<JsonValue> {
-        Ok(match token {
-            Token::Null => JsonValue::Null,
-            Token::Boolen(b) => JsonValue::Boolen(b),
-            Token::Number(n) => JsonValue::Number(n),
-            Token::String(s) => JsonValue::String(s),
-            Token::BraceOn => self.parse_object()?,
-            Token::BracketOn => self.parse_array()?,
-            _ => return Err(JsonError::unexpected_token(token)),
-        })
+    fn parse_value(&mut self, token: Token<'a>) -> JsonResult<V> {
+        match self.capture {
+            Some(capture) if self.at.is_some_and(|at| self.raw.nodes[at].raw) => {
+                let start = self.tokenizer.token_start();
+                self.parse_token(token)?;
+                Ok(capture(&self.source[start..self.offset()]))
+            }
+            _ => self.parse_token(token),
+        }
+    }
+
+    // a value under the current one, `segment` finds its trie node among the children
+    fn child(
+        &mut self,
+        segment: impl FnOnce(&RawNode) -> Option<usize>,
+        token: Token<'a>,
+    ) -> JsonResult<V> {
+        let parent = self.at;
+        self.at = parent.and_then(|at| segment(&self.raw.nodes[at]));
+        let value = self.parse_value(token);
+        self.at = parent;
+        value
+    }
+
+    // the token just consumed, where nothing like it may go
+    pub(crate) fn unexpected(&self, token: Token) -> JsonError {
+        JsonError::unexpected_token(token, self.tokenizer.token_start())
+    }
+
+    fn parse_token(&mut self, token: Token<'a>) -> JsonResult<V> {
+        match token {
+            Token::BraceOn => self.parse_object(),
+            Token::BracketOn => self.parse_array(),
+            token => V::scalar(token).map_err(|token| self.unexpected(token)),
+        }
     }
 
     // return json value
-    fn value(&mut self) -> JsonResult<JsonValue> {
+    pub(crate) fn value(&mut self) -> JsonResult<V> {
         let token = self.consume()?;
         self.parse_value(token)
     }
 
-    fn parse_object(&mut self) -> JsonResult<JsonValue> {
-        let mut ret = BTreeMap::new();
-
+    fn parse_key(&mut self, token: Token<'a>) -> JsonResult<Cow<'a, str>> {
+        let key = match token {
+            Token::String(key) => key,
+            Token::Wtf8(key) => Cow::Owned(key.to_string_lossy()),
+            token => return Err(self.unexpected(token)),
+        };
         match self.consume()? {
-            Token::BraceOff => return Ok(ret.into()),
-            Token::String(key) => {
-                match self.consume()? {
-                    Token::Colon => (),
-                    token => return Err(JsonError::unexpected_token(token)),
-                }
-                let value = self.value()?;
-                ret.insert(key, value);
-            }
-            token => return Err(JsonError::unexpected_token(token)),
+            Token::Colon => Ok(key),
+            token => Err(self.unexpected(token)),
         }
+    }
 
+    fn parse_object(&mut self) -> JsonResult<V> {
+        let mut ret = BTreeMap::new();
+
+        let mut token = self.consume()?;
+        if token == Token::BraceOff {
+            return Ok(V::object(ret));
+        }
         loop {
-            match self.consume()? {
-                Token::Comma => {
-                    let key = match self.consume()? {
-                        Token::String(key) => key,
-                        token => return Err(JsonError::unexpected_token(token)),
-                    };
-                    match self.consume()? {
-                        Token::Colon => (),
-                        token => return Err(JsonError::unexpected_token(token)),
-                    }
-                    let value = self.value()?;
-                    ret.insert(key, value);
-                }
+            let key = self.parse_key(token)?;
+            let token_value = self.consume()?;
+            let value = self.child(|node| node.keys.get(&*key).copied(), token_value)?;
+            ret.insert(V::key(key), value);
 
+            match self.consume()? {
+                Token::Comma => token = self.consume()?,
                 Token::BraceOff => break,
-                token => return Err(JsonError::unexpected_token(token)),
+                token => return Err(self.unexpected(token)),
             }
         }
 
-        Ok(ret.into())
+        Ok(V::object(ret))
     }
 
     /*
      * [1,2,3]
      * [[a,b,c], d, e]
      */
-    fn parse_array(&mut self) -> JsonResult<JsonValue> {
+    fn parse_array(&mut self) -> JsonResult<V> {
         let mut ret = Vec::new();
         match self.consume()? {
-            Token::BracketOff => return Ok(ret.into()),
-            token => ret.push(self.parse_value(token)?),
+            Token::BracketOff => return Ok(V::array(ret)),
+            token => ret.push(self.child(|node| node.indexes.get(&0).copied(), token)?),
         }
 
         loop {
             match self.consume()? {
-                Token::Comma => ret.push(self.value()?),
+                Token::Comma => {
+                    let token = self.consume()?;
+                    let i = ret.len();
+                    ret.push(self.child(|node| node.indexes.get(&i).copied(), token)?);
+                }
                 Token::BracketOff => break,
-                token => return Err(JsonError::unexpected_token(token)),
+                token => return Err(self.unexpected(token)),
             }
         }
 
-        Ok(ret.into())
+        Ok(V::array(ret))
     }
 }
 
 pub fn parse(json: &str) -> JsonResult<JsonValue> {
     let mut parser = Parser::new(json);
-    Ok(parser.value()?)
+    parser.value()
+}
+
+pub fn parse_with(json: &str, options: ParseOptions) -> JsonResult<JsonValue> {
+    let mut parser = Parser::with_options(json, options);
+    parser.value()
+}
+
+// Like `parse_with`, but the values at the RFC 6901 `pointers` are kept as
+// `JsonValue::Raw`, their source text as it is. They are still validated.
+pub fn parse_with_raw(
+    json: &str,
+    options: ParseOptions,
+    pointers: &[&str],
+) -> JsonResult<JsonValue> {
+    let mut parser = Parser::with_options(json, options);
+    parser.raw = RawPointers::new(pointers);
+    parser.at = Some(0);
+    parser.capture = Some(|text| JsonValue::Raw(text.to_string()));
+    parser.value()
+}
+
+// like `parse`, but anything after the value is an error
+pub fn parse_complete(json: &str) -> JsonResult<JsonValue> {
+    let mut parser = Parser::new(json);
+    let value = parser.value()?;
+    match parser.consume() {
+        Err(JsonError::UnexpectedEndOfJson) => Ok(value),
+        Ok(token) => Err(parser.unexpected(token)),
+        Err(err) => Err(err),
+    }
 }
 
 #[cfg(test)]
@@ -132,7 +319,7 @@ mod tests {
         let token = source.consume().unwrap();
         assert_eq!(token, Token::Boolen(false));
         let token = source.consume().unwrap();
-        assert_eq!(token, Token::String("abc  d ".to_string()));
+        assert_eq!(token, Token::String("abc  d ".into()));
         let token = source.consume().unwrap();
         assert_eq!(token, Token::Number(1234.into()));
         let token = source.consume().unwrap();
@@ -248,4 +435,68 @@ mod tests {
 
         println!("{:?}", ret);
     }
+
+    #[test]
+    fn parse_lossless_number() {
+        assert_eq!(parse("18446744073709551615").unwrap(), u64::MAX.into());
+        assert_eq!(parse("-170141183460469231731687303715884105728").unwrap(), i128::MIN.into());
+        assert_eq!(parse("1.5").unwrap(), 1.5.into());
+        assert_eq!(parse("-0").unwrap().dump(), "-0");
+
+        // floats stay floats through dump and parse
+        for s in ["1.0", "-2.0", "1e300", "1e-7", "0.1", "-0", "18446744073709551616.0"] {
+            let value = parse(s).unwrap();
+            assert_eq!(parse(&value.dump()), Ok(value), "{}", s);
+        }
+        assert_eq!(JsonValue::from(6.0).dump(), "6.0");
+    }
+
+    #[test]
+    fn surrogate_policies() {
+        let with = |s: &str, surrogates| {
+            parse_with(s, ParseOptions { surrogates })
+        };
+        for policy in [SurrogatePolicy::Error, SurrogatePolicy::Replace, SurrogatePolicy::Preserve] {
+            assert_eq!(with(r#""𝄞""#, policy), Ok("𝄞".into()));
+        }
+
+        assert_eq!(parse(r#""a\ud800b""#), Err(JsonError::UnpairedSurrogate(2)));
+        assert_eq!(parse(r#"["\udc00"]"#), Err(JsonError::UnpairedSurrogate(2)));
+        assert_eq!(parse(r#""\ud800A""#), Err(JsonError::UnpairedSurrogate(1)));
+        assert_eq!(parse(r#""\u12g4""#), Err(JsonError::InvalidUnicodeEscape(1)));
+        assert_eq!(parse(r#""\ud800\u12""#), Err(JsonError::InvalidUnicodeEscape(7)));
+
+        let replace = |s| with(s, SurrogatePolicy::Replace).unwrap();
+        assert_eq!(replace(r#""a\ud800b""#), "a\u{FFFD}b".into());
+        assert_eq!(replace(r#""\ud800A""#), "\u{FFFD}A".into());
+        assert_eq!(replace(r#""\ud800\ud800\udc00""#), "\u{FFFD}\u{10000}".into());
+
+        let s = r#"{"k\udc00":"a\ud800b𝄞"}"#;
+        let value = with(s, SurrogatePolicy::Preserve).unwrap();
+        let string = value.pointer("/k\u{FFFD}").unwrap();
+        assert!(matches!(string, JsonValue::Wtf8(w) if w.to_string_lossy() == "a\u{FFFD}b𝄞"));
+        assert_eq!(value.dump(), r#"{"k�":"a\ud800b𝄞"}"#);
+    }
+
+    #[test]
+    fn raw_pointers() {
+        let s = r#"{"id": 1, "payload": {"b": 2,  "a": [1.50, "\u00e9"]}, "list": [{}, [ ]]}"#;
+        let pointers = ["/payload", "/list/1", "/id/x", "/list/01", "nope"];
+        let value = parse_with_raw(s, ParseOptions::default(), &pointers).unwrap();
+        let payload = r#"{"b": 2,  "a": [1.50, "\u00e9"]}"#;
+        assert_eq!(value.pointer("/payload"), Some(&JsonValue::Raw(payload.to_string())));
+        assert_eq!(value.pointer("/list/1"), Some(&JsonValue::Raw("[ ]".to_string())));
+        assert_eq!(value.pointer("/id"), Some(&1.into()));
+        assert_eq!(value.dump(), format!(r#"{{"id":1,"list":[{{}},[ ]],"payload":{}}}"#, payload));
+
+        let root = |s| parse_with_raw(s, ParseOptions::default(), &[""]);
+        assert_eq!(root(" [1 ,2] "), Ok(JsonValue::Raw("[1 ,2]".to_string())));
+        // still validated
+        assert!(root("[1,]").is_err());
+
+        // keys as they are, the pointer escaped
+        let s = r#"{"a/b": {"~": 0}}"#;
+        let value = parse_with_raw(s, ParseOptions::default(), &["/a~1b/~0"]);
+        assert_eq!(value.unwrap().pointer("/a~1b/~0"), Some(&JsonValue::Raw("0".to_string())));
+    }
 }
@@ -0,0 +1,70 @@
+use std::fmt::Display;
+
+// 1-based line and column (in chars) of a byte offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn locate(source: &str, offset: usize) -> Self {
+        LineIndex::new(source).position(offset)
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {} column {}", self.line, self.column)
+    }
+}
+
+// start offset of every line, to locate many offsets in the same source
+pub struct LineIndex<'a> {
+    source: &'a str,
+    lines: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let mut lines = vec![0];
+        lines.extend(
+            source
+                .bytes()
+                .enumerate()
+                .filter(|&(_, ch)| ch == b'\n')
+                .map(|(i, _)| i + 1),
+        );
+        LineIndex { source, lines }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.source.len());
+        let line = self.lines.partition_point(|&start| start <= offset) - 1;
+        let start = self.lines[line];
+        let column = match self.source.get(start..offset) {
+            Some(prefix) => prefix.chars().count(),
+            None => offset - start,
+        };
+        Position {
+            offset,
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locate() {
+        let s = "{\n  \"é\": x\n}";
+        assert_eq!(Position::locate(s, 0), Position { offset: 0, line: 1, column: 1 });
+        assert_eq!(Position::locate(s, 10), Position { offset: 10, line: 2, column: 8 });
+        assert_eq!(Position::locate(s, 99), Position { offset: 13, line: 3, column: 2 });
+        assert_eq!(Position::locate(s, 10).to_string(), "line 2 column 8");
+    }
+}
@@ -0,0 +1,442 @@
+use std::{collections::VecDeque, mem};
+
+use crate::{
+    error::JsonError,
+    parser::SurrogatePolicy,
+    tokenizer::{Token, Tokenizer},
+    value::JsonValue,
+    JsonResult,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(String),
+    // null, boolean, number or string
+    Value(JsonValue),
+}
+
+// a token split across `feed` calls, kept as raw bytes until it ends
+enum Partial {
+    None,
+    // the raw bytes from the opening quote on, `escaped` after a backslash
+    String { raw: Vec<u8>, escaped: bool },
+    Number(Vec<u8>),
+    Literal { text: &'static [u8], matched: usize },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Expect {
+    // a value at the top level, after `:` or after `,` in an array
+    Value,
+    // after `[`, a value or `]`
+    FirstItem,
+    // after an array item, `,` or `]`
+    ItemEnd,
+    // after `{`, a key or `}`
+    FirstKey,
+    // after `,` in an object
+    Key,
+    Colon,
+    // after an object member, `,` or `}`
+    MemberEnd,
+}
+
+#[derive(PartialEq)]
+enum Container {
+    Array,
+    Object,
+}
+
+// Push parser for input that arrives in chunks. Bytes go in with `feed`, events
+// come out of `next_event` as soon as each one is complete. A token cut by the
+// chunk boundary (a string, a number, a `\u` escape, a multi-byte char) is kept
+// until the rest arrives, so `feed` only fails on real syntax errors and running
+// out of input is reported by `finish` as `UnexpectedEndOfJson`.
+// Any number of top level values may follow each other.
+pub struct PushParser {
+    events: VecDeque<Event>,
+    stack: Vec<Container>,
+    expect: Expect,
+    partial: Partial,
+    offset: usize,
+    failed: bool,
+    surrogates: SurrogatePolicy,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        PushParser {
+            events: VecDeque::new(),
+            stack: Vec::new(),
+            expect: Expect::Value,
+            partial: Partial::None,
+            offset: 0,
+            failed: false,
+            surrogates: SurrogatePolicy::Error,
+        }
+    }
+
+    // lone surrogate escapes are handled as `ParseOptions::surrogates` says
+    pub fn with_surrogates(surrogates: SurrogatePolicy) -> Self {
+        PushParser {
+            surrogates,
+            ..Self::new()
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> JsonResult<()> {
+        if self.failed {
+            return Err(JsonError::parsing_failed("feed after a syntax error"));
+        }
+        for &byte in chunk {
+            if let Err(err) = self.byte(byte) {
+                self.failed = true;
+                return Err(err);
+            }
+            self.offset += 1;
+        }
+        Ok(())
+    }
+
+    // no more input: completes a trailing top level number, fails if a value is unfinished
+    pub fn finish(&mut self) -> JsonResult<()> {
+        if self.failed {
+            return Err(JsonError::parsing_failed("finish after a syntax error"));
+        }
+        match mem::replace(&mut self.partial, Partial::None) {
+            Partial::None => (),
+            Partial::Number(raw) => self.number(&raw, true)?,
+            _ => return Err(JsonError::UnexpectedEndOfJson),
+        }
+        if !self.stack.is_empty() {
+            return Err(JsonError::UnexpectedEndOfJson);
+        }
+        Ok(())
+    }
+
+    pub fn next_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    // in the middle of a value, `finish` now would fail
+    pub fn needs_more(&self) -> bool {
+        !self.stack.is_empty() || !matches!(self.partial, Partial::None)
+    }
+
+    // bytes consumed so far, the offending byte on error
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn byte(&mut self, byte: u8) -> JsonResult<()> {
+        match &mut self.partial {
+            Partial::None => (),
+            Partial::String { raw, escaped } => {
+                raw.push(byte);
+                if *escaped {
+                    *escaped = false;
+                } else if byte == b'\\' {
+                    *escaped = true;
+                } else if byte == b'"' {
+                    let raw = mem::take(raw);
+                    self.partial = Partial::None;
+                    return self.string(&raw);
+                }
+                return Ok(());
+            }
+            // a number only ends at the first byte that can't continue it
+            Partial::Number(raw) => {
+                if matches!(byte, b'0'..=b'9' | b'+' | b'-' | b'.' | b'e' | b'E') {
+                    raw.push(byte);
+                    return Ok(());
+                }
+                let raw = mem::take(raw);
+                self.partial = Partial::None;
+                self.number(&raw, false)?;
+            }
+            Partial::Literal { text, matched } => {
+                if byte != text[*matched] {
+                    return Err(JsonError::unexpected_character(byte, self.offset));
+                }
+                *matched += 1;
+                if *matched == text.len() {
+                    let value = match text[0] {
+                        b't' => JsonValue::Boolen(true),
+                        b'f' => JsonValue::Boolen(false),
+                        _ => JsonValue::Null,
+                    };
+                    self.partial = Partial::None;
+                    self.value(value);
+                }
+                return Ok(());
+            }
+        }
+
+        match byte {
+            b' ' | b'\t' | b'\n' | b'\r' => (),
+            b'{' => {
+                self.begin_value(byte)?;
+                self.stack.push(Container::Object);
+                self.events.push_back(Event::StartObject);
+                self.expect = Expect::FirstKey;
+            }
+            b'[' => {
+                self.begin_value(byte)?;
+                self.stack.push(Container::Array);
+                self.events.push_back(Event::StartArray);
+                self.expect = Expect::FirstItem;
+            }
+            b'}' if matches!(self.expect, Expect::FirstKey | Expect::MemberEnd) => {
+                self.stack.pop();
+                self.events.push_back(Event::EndObject);
+                self.value_done();
+            }
+            b']' if matches!(self.expect, Expect::FirstItem | Expect::ItemEnd) => {
+                self.stack.pop();
+                self.events.push_back(Event::EndArray);
+                self.value_done();
+            }
+            b',' if self.expect == Expect::ItemEnd => self.expect = Expect::Value,
+            b',' if self.expect == Expect::MemberEnd => self.expect = Expect::Key,
+            b':' if self.expect == Expect::Colon => self.expect = Expect::Value,
+            b'"' => {
+                if !matches!(self.expect, Expect::Key | Expect::FirstKey) {
+                    self.begin_value(byte)?;
+                }
+                self.partial = Partial::String {
+                    raw: vec![byte],
+                    escaped: false,
+                };
+            }
+            b'-' | b'0'..=b'9' => {
+                self.begin_value(byte)?;
+                self.partial = Partial::Number(vec![byte]);
+            }
+            b't' | b'f' | b'n' => {
+                self.begin_value(byte)?;
+                let text: &'static [u8] = match byte {
+                    b't' => b"true",
+                    b'f' => b"false",
+                    _ => b"null",
+                };
+                self.partial = Partial::Literal { text, matched: 1 };
+            }
+            _ => return Err(JsonError::unexpected_character(byte, self.offset)),
+        }
+        Ok(())
+    }
+
+    fn begin_value(&self, byte: u8) -> JsonResult<()> {
+        match self.expect {
+            Expect::Value | Expect::FirstItem => Ok(()),
+            _ => Err(JsonError::unexpected_character(byte, self.offset)),
+        }
+    }
+
+    fn value_done(&mut self) {
+        self.expect = match self.stack.last() {
+            None => Expect::Value,
+            Some(Container::Array) => Expect::ItemEnd,
+            Some(Container::Object) => Expect::MemberEnd,
+        };
+    }
+
+    fn value(&mut self, value: JsonValue) {
+        self.events.push_back(Event::Value(value));
+        self.value_done();
+    }
+
+    // the complete token is handed to the regular tokenizer for decoding, its errors
+    // are moved to where the token starts in the input
+    fn string(&mut self, raw: &[u8]) -> JsonResult<()> {
+        let start = self.offset + 1 - raw.len();
+        let text = std::str::from_utf8(raw)
+            .map_err(|e| JsonError::InvalidEncoding(start + e.valid_up_to()))?;
+        let token = Tokenizer::with_surrogates(text, self.surrogates)
+            .next()
+            .map_err(|e| e.map_offset(|offset| start + offset))?;
+        let key = matches!(self.expect, Expect::Key | Expect::FirstKey);
+        let value = match token {
+            Token::String(s) => JsonValue::String(s.into_owned()),
+            Token::Wtf8(s) if key => JsonValue::String(s.to_string_lossy()),
+            Token::Wtf8(s) => JsonValue::Wtf8(s),
+            _ => unreachable!(),
+        };
+        match value {
+            JsonValue::String(s) if key => {
+                self.events.push_back(Event::Key(s));
+                self.expect = Expect::Colon;
+            }
+            value => self.value(value),
+        }
+        Ok(())
+    }
+
+    // `raw` ended just before `self.offset`, by the end of the input or by a byte that
+    // can't go on a number
+    fn number(&mut self, raw: &[u8], at_end: bool) -> JsonResult<()> {
+        let start = self.offset - raw.len();
+        // only ascii digits, signs, dots and exponents were collected
+        let text: String = raw.iter().map(|&b| b as char).collect();
+        let token = match Tokenizer::new(&text).next() {
+            Err(JsonError::UnexpectedEndOfJson) if !at_end => Err(JsonError::InvalidNumber(start)),
+            token => token.map_err(|e| e.map_offset(|o| start + o)),
+        };
+        match token? {
+            Token::Number(n) => self.value(JsonValue::Number(n)),
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
+// Assembles the events of a `PushParser` back into complete values.
+#[derive(Default)]
+pub struct ValueBuilder {
+    // open containers, each with the key it will be stored under in its parent
+    stack: Vec<(JsonValue, Option<String>)>,
+    key: Option<String>,
+}
+
+impl ValueBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // returns a top level value once its last event is pushed
+    pub fn push(&mut self, event: Event) -> Option<JsonValue> {
+        match event {
+            Event::StartObject => self.stack.push((JsonValue::Object(Default::default()), self.key.take())),
+            Event::StartArray => self.stack.push((JsonValue::Array(Vec::new()), self.key.take())),
+            Event::Key(key) => self.key = Some(key),
+            Event::Value(value) => return self.insert(value),
+            Event::EndObject | Event::EndArray => {
+                let (container, key) = self.stack.pop()?;
+                self.key = key;
+                return self.insert(container);
+            }
+        }
+        None
+    }
+
+    fn insert(&mut self, value: JsonValue) -> Option<JsonValue> {
+        match self.stack.last_mut() {
+            None => return Some(value),
+            Some((JsonValue::Array(array), _)) => array.push(value),
+            Some((JsonValue::Object(object), _)) => {
+                object.insert(self.key.take().unwrap_or_default(), value);
+            }
+            Some(_) => unreachable!(),
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn values(chunks: &[&[u8]]) -> JsonResult<Vec<JsonValue>> {
+        let mut parser = PushParser::new();
+        let mut builder = ValueBuilder::new();
+        let mut values = Vec::new();
+        for chunk in chunks {
+            parser.feed(chunk)?;
+            while let Some(event) = parser.next_event() {
+                values.extend(builder.push(event));
+            }
+        }
+        parser.finish()?;
+        while let Some(event) = parser.next_event() {
+            values.extend(builder.push(event));
+        }
+        Ok(values)
+    }
+
+    #[test]
+    fn byte_at_a_time() {
+        let s = r#"{"a": [1, -2.5e3, true, null], "bé": "x\"y𝄞", "c": {"é": []}}"#;
+        let chunks: Vec<&[u8]> = s.as_bytes().chunks(1).collect();
+        assert_eq!(values(&chunks), Ok(vec![parse(s).unwrap()]));
+    }
+
+    #[test]
+    fn events_as_soon_as_possible() {
+        let mut parser = PushParser::new();
+        parser.feed(br#"{"key": "val"#).unwrap();
+        assert_eq!(parser.next_event(), Some(Event::StartObject));
+        assert_eq!(parser.next_event(), Some(Event::Key("key".to_string())));
+        assert_eq!(parser.next_event(), None);
+        parser.feed(br#"ue", "n": 12"#).unwrap();
+        assert_eq!(parser.next_event(), Some(Event::Value("value".into())));
+        assert_eq!(parser.next_event(), Some(Event::Key("n".to_string())));
+        // 12 may go on, it is only complete at the next byte
+        assert_eq!(parser.next_event(), None);
+        parser.feed(b"3}").unwrap();
+        assert_eq!(parser.next_event(), Some(Event::Value(123.into())));
+        assert_eq!(parser.next_event(), Some(Event::EndObject));
+        assert!(!parser.needs_more());
+    }
+
+    #[test]
+    fn multiple_values() {
+        assert_eq!(
+            values(&[b"1 [2", b"] \"a", b"\" 4"]),
+            Ok(vec![1.into(), crate::array![2], "a".into(), 4.into()])
+        );
+    }
+
+    #[test]
+    fn eof_is_not_a_syntax_error() {
+        for s in [r#"{"a": "\u00"#, "[1, 2", "tr", r#"{"a""#, "[-", "1e", "[1.5e+"] {
+            let mut parser = PushParser::new();
+            parser.feed(s.as_bytes()).unwrap();
+            assert!(parser.needs_more());
+            assert_eq!(parser.finish(), Err(JsonError::UnexpectedEndOfJson));
+        }
+        for s in ["[1,]", "{1: 2}", "tru,", "[1 2]", "{\"a\" 1}"] {
+            let mut parser = PushParser::new();
+            let err = parser.feed(s.as_bytes()).unwrap_err();
+            assert!(err.is_syntax(), "{}: {:?}", s, err);
+            assert!(parser.feed(b"1").is_err());
+        }
+        let mut parser = PushParser::new();
+        assert_eq!(parser.feed(b"[\"ab\xFF\"]"), Err(JsonError::InvalidEncoding(4)));
+
+        // offsets count from the start of the input, not of the string
+        let mut parser = PushParser::new();
+        let err = parser.feed(br#"[1, "ab\ud800"]"#).unwrap_err();
+        assert_eq!(err, JsonError::UnpairedSurrogate(7));
+        let mut parser = PushParser::new();
+        assert_eq!(parser.feed(br#"{"a": "\u12x"}"#), Err(JsonError::InvalidUnicodeEscape(7)));
+        let mut parser = PushParser::new();
+        assert_eq!(parser.feed(b"[0, 1e]"), Err(JsonError::InvalidNumber(4)));
+    }
+
+    #[test]
+    fn surrogate_policy() {
+        let s = r#"{"\ud800": ["\udc00", "a\ud800"]}"#;
+        for surrogates in [SurrogatePolicy::Replace, SurrogatePolicy::Preserve] {
+            let mut parser = PushParser::with_surrogates(surrogates);
+            let mut builder = ValueBuilder::new();
+            parser.feed(s.as_bytes()).unwrap();
+            let mut value = None;
+            while let Some(event) = parser.next_event() {
+                value = value.or(builder.push(event));
+            }
+            let options = crate::ParseOptions { surrogates };
+            assert_eq!(value, Some(crate::parse_with(s, options).unwrap()));
+        }
+    }
+}
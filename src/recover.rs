@@ -0,0 +1,439 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use crate::{
+    error::JsonError,
+    position::{LineIndex, Position},
+    tokenizer::{Token, Tokenizer},
+    value::JsonValue,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub error: JsonError,
+    pub position: Position,
+    pub severity: Severity,
+}
+
+// what the lookahead token means to the recovering parser
+#[derive(PartialEq)]
+enum Next {
+    End,
+    Comma,
+    Colon,
+    BraceOff,
+    BracketOff,
+    Key,
+    Value,
+}
+
+#[derive(PartialEq)]
+enum Container {
+    Array,
+    Object,
+}
+
+// Parses as much as it can: every problem becomes a diagnostic and a `Null`
+// placeholder, "" for a broken string, then parsing resumes at the next `,` `}` or `]`.
+struct RecoveringParser<'a> {
+    tokenizer: Tokenizer<'a>,
+    lookahead: Option<(Token<'a>, usize)>,
+    at_end: bool,
+    open: Vec<Container>,
+    diagnostics: Vec<(JsonError, usize, Severity)>,
+    // one error per damaged region, follow-up errors are noise until we resync
+    panicking: bool,
+    eof_reported: bool,
+}
+
+impl<'a> RecoveringParser<'a> {
+    fn new(source: &'a str) -> Self {
+        RecoveringParser {
+            tokenizer: Tokenizer::new(source),
+            lookahead: None,
+            at_end: false,
+            open: Vec::new(),
+            diagnostics: Vec::new(),
+            panicking: false,
+            eof_reported: false,
+        }
+    }
+
+    // Next good token, tokenizer errors are reported at their own offset and skipped.
+    // A string that fails is skipped to its closing quote and stands in as "", its
+    // contents aren't read as structure.
+    fn fetch(&mut self) -> Option<(Token<'a>, usize)> {
+        loop {
+            match self.tokenizer.next() {
+                Ok(token) => return Some((token, self.tokenizer.token_start())),
+                Err(JsonError::UnexpectedEndOfJson) => return None,
+                Err(err) => {
+                    let start = self.tokenizer.token_start();
+                    let at = err.offset().unwrap_or(start);
+                    self.report(err, at);
+                    if self.tokenizer.skip_string() {
+                        return Some((Token::String(Cow::Borrowed("")), start));
+                    }
+                }
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Next {
+        if self.lookahead.is_none() && !self.at_end {
+            self.lookahead = self.fetch();
+            self.at_end = self.lookahead.is_none();
+        }
+        match self.lookahead {
+            None => Next::End,
+            Some((Token::Comma, _)) => Next::Comma,
+            Some((Token::Colon, _)) => Next::Colon,
+            Some((Token::BraceOff, _)) => Next::BraceOff,
+            Some((Token::BracketOff, _)) => Next::BracketOff,
+            Some((Token::String(_), _)) => Next::Key,
+            Some(_) => Next::Value,
+        }
+    }
+
+    fn bump(&mut self) -> Option<(Token<'a>, usize)> {
+        self.peek();
+        let next = self.lookahead.take();
+        if let Some((Token::Comma | Token::BraceOff | Token::BracketOff, _)) = next {
+            self.panicking = false;
+        }
+        next
+    }
+
+    fn report(&mut self, error: JsonError, at: usize) {
+        if !self.panicking {
+            self.diagnostics.push((error, at, Severity::Error));
+            self.panicking = true;
+        }
+    }
+
+    fn warn(&mut self, error: JsonError, at: usize) {
+        self.diagnostics.push((error, at, Severity::Warning));
+    }
+
+    fn report_eof(&mut self) {
+        if !self.eof_reported {
+            let at = self.tokenizer.index();
+            self.diagnostics
+                .push((JsonError::UnexpectedEndOfJson, at, Severity::Error));
+            self.eof_reported = true;
+        }
+    }
+
+    // report the lookahead token without consuming it
+    fn unexpected(&mut self) {
+        self.peek();
+        match self.lookahead {
+            Some((ref token, at)) => {
+                let err = JsonError::UnexpectedToken(token.to_string(), at);
+                self.report(err, at);
+            }
+            None => self.report_eof(),
+        }
+    }
+
+    // a closer of an enclosing container ends the current one and is left for its owner
+    fn closes_outer(&self, container: Container) -> bool {
+        self.open[..self.open.len() - 1].contains(&container)
+    }
+
+    fn parse_value(&mut self) -> JsonValue {
+        match self.peek() {
+            Next::End => {
+                self.report_eof();
+                return JsonValue::Null;
+            }
+            Next::Comma | Next::BraceOff | Next::BracketOff => {
+                self.unexpected();
+                return JsonValue::Null;
+            }
+            _ => (),
+        }
+        let (token, at) = self.bump().unwrap();
+        match token {
+            Token::Null => JsonValue::Null,
+            Token::Boolen(b) => JsonValue::Boolen(b),
+            Token::Number(n) => JsonValue::Number(n),
+            Token::String(s) => JsonValue::String(s.into_owned()),
+            Token::BraceOn => self.parse_object(),
+            Token::BracketOn => self.parse_array(),
+            token => {
+                self.report(JsonError::unexpected_token(token, at), at);
+                JsonValue::Null
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> JsonValue {
+        self.open.push(Container::Array);
+        let mut ret = Vec::new();
+        let mut expect_value = true;
+        let mut after_comma = false;
+
+        loop {
+            match self.peek() {
+                Next::End => {
+                    self.report_eof();
+                    break;
+                }
+                Next::BracketOff => {
+                    if after_comma {
+                        self.unexpected();
+                    }
+                    self.bump();
+                    break;
+                }
+                Next::BraceOff => {
+                    self.unexpected();
+                    if self.closes_outer(Container::Object) {
+                        break;
+                    }
+                    // a stray closer that matches nothing open is skipped
+                    self.bump();
+                }
+                Next::Comma => {
+                    // [1,,2]
+                    if expect_value {
+                        self.unexpected();
+                        ret.push(JsonValue::Null);
+                    }
+                    self.bump();
+                    expect_value = true;
+                    after_comma = true;
+                }
+                _ => {
+                    // [1 2]
+                    if !expect_value {
+                        self.unexpected();
+                    }
+                    ret.push(self.parse_value());
+                    expect_value = false;
+                    after_comma = false;
+                }
+            }
+        }
+
+        self.open.pop();
+        JsonValue::Array(ret)
+    }
+
+    fn parse_object(&mut self) -> JsonValue {
+        self.open.push(Container::Object);
+        let mut ret = BTreeMap::new();
+        let mut expect_key = true;
+        let mut after_comma = false;
+
+        loop {
+            match self.peek() {
+                Next::End => {
+                    self.report_eof();
+                    break;
+                }
+                Next::BraceOff => {
+                    if after_comma {
+                        self.unexpected();
+                    }
+                    self.bump();
+                    break;
+                }
+                Next::BracketOff => {
+                    self.unexpected();
+                    if self.closes_outer(Container::Array) {
+                        break;
+                    }
+                    // a stray closer that matches nothing open is skipped
+                    self.bump();
+                }
+                Next::Comma => {
+                    if expect_key {
+                        self.unexpected();
+                    }
+                    self.bump();
+                    expect_key = true;
+                    after_comma = true;
+                }
+                Next::Key => {
+                    // {"a": 1 "b": 2}
+                    if !expect_key {
+                        self.unexpected();
+                    }
+                    let (key, at) = match self.bump() {
+                        Some((Token::String(key), at)) => (key.into_owned(), at),
+                        _ => unreachable!(),
+                    };
+                    if self.peek() == Next::Colon {
+                        self.bump();
+                    } else {
+                        self.unexpected();
+                    }
+                    let value = self.parse_value();
+                    if ret.insert(key.clone(), value).is_some() {
+                        self.warn(JsonError::DuplicateKey(key), at);
+                    }
+                    expect_key = false;
+                    after_comma = false;
+                }
+                Next::Colon | Next::Value => {
+                    // not a key, drop the whole member
+                    self.unexpected();
+                    while !matches!(
+                        self.peek(),
+                        Next::End | Next::Comma | Next::BraceOff | Next::BracketOff
+                    ) {
+                        self.parse_value();
+                    }
+                    expect_key = false;
+                    after_comma = false;
+                }
+            }
+        }
+
+        self.open.pop();
+        JsonValue::Object(ret)
+    }
+}
+
+// Parse a possibly broken document, returning the best effort value together with
+// every problem found. Input `parse` accepts gives the same value, but not always
+// without diagnostics: a duplicate key is a `Warning`, and content after the value,
+// which `parse` ignores, is an `Error`.
+pub fn parse_recovering(json: &str) -> (JsonValue, Vec<Diagnostic>) {
+    let mut parser = RecoveringParser::new(json);
+    let value = parser.parse_value();
+
+    // trailing content, unless it is the stray token that was just reported
+    if parser.peek() != Next::End {
+        let at = parser.lookahead.as_ref().map(|(_, at)| *at);
+        if parser.diagnostics.last().map(|d| d.1) != at {
+            parser.panicking = false;
+            parser.unexpected();
+        }
+    }
+
+    let lines = LineIndex::new(json);
+    let mut diagnostics: Vec<_> = parser
+        .diagnostics
+        .into_iter()
+        .map(|(error, at, severity)| Diagnostic {
+            error,
+            position: lines.position(at),
+            severity,
+        })
+        .collect();
+    diagnostics.sort_by_key(|d| d.position.offset);
+    (value, diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, object, parse};
+
+    fn summary(diagnostics: &[Diagnostic]) -> Vec<(usize, usize, String, Severity)> {
+        diagnostics
+            .iter()
+            .map(|d| (d.position.line, d.position.column, d.error.to_string(), d.severity))
+            .collect()
+    }
+
+    #[test]
+    fn valid_input() {
+        let s = r#"{"a": [1, 2, {"b": null}], "c": "d"}"#;
+        assert_eq!(parse_recovering(s), (parse(s).unwrap(), vec![]));
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let s = r#"{
+  "name": "app",
+  "port": 80 80,
+  "debug": tru,
+  "tags": ["a",, "b",],
+  "name": "dup"
+  "x": 1
+}"#;
+        let (value, diagnostics) = parse_recovering(s);
+        assert_eq!(
+            value,
+            object! {
+                "name" => "dup",
+                "port" => 80,
+                "debug" => JsonValue::Null,
+                "tags" => array!["a", JsonValue::Null, "b"],
+                "x" => 1
+            }
+        );
+        assert_eq!(
+            summary(&diagnostics),
+            vec![
+                (3, 14, "unexpected token `80` at byte 32".to_string(), Severity::Error),
+                (4, 15, "unexpected character ',' at byte 50".to_string(), Severity::Error),
+                (5, 16, "unexpected token `,` at byte 67".to_string(), Severity::Error),
+                (5, 22, "unexpected token `]` at byte 73".to_string(), Severity::Error),
+                (6, 3, "duplicate key `name`".to_string(), Severity::Warning),
+                (7, 3, "unexpected token `\"x\"` at byte 94".to_string(), Severity::Error),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_containers() {
+        let (value, diagnostics) = parse_recovering(r#"[1, {"a": 2"#);
+        assert_eq!(value, array![1, object! {"a" => 2}]);
+        assert_eq!(
+            summary(&diagnostics),
+            vec![(1, 12, "unexpected end of json".to_string(), Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn mismatched_closer() {
+        // the `}` belongs to the object, so the array ends there
+        let (value, diagnostics) = parse_recovering(r#"{"a": [1, 2}"#);
+        assert_eq!(value, object! {"a" => array![1, 2]});
+        assert_eq!(
+            summary(&diagnostics),
+            vec![(1, 12, "unexpected token `}` at byte 11".to_string(), Severity::Error)]
+        );
+
+        let (value, diagnostics) = parse_recovering(r#"{"a": {x: 1}, "b": 2]}"#);
+        assert_eq!(value, object! {"a" => object! {}, "b" => 2});
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].error, JsonError::UnexpectedCharacter('x', 7));
+        assert_eq!(diagnostics[1].position.column, 21);
+    }
+
+    #[test]
+    fn bad_string() {
+        // the rest of the string is skipped, not read as structure
+        let (value, diagnostics) = parse_recovering(r#"{"a": "\u12g4 abc", "b": 1, "c": 2}"#);
+        assert_eq!(value, object! {"a" => "", "b" => 1, "c" => 2});
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].error, JsonError::InvalidUnicodeEscape(7));
+        assert_eq!(diagnostics[0].position.offset, 7);
+
+        let (value, diagnostics) = parse_recovering(r#"["\ud800 \" ]", 1]"#);
+        assert_eq!(value, array!["", 1]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].error, JsonError::UnpairedSurrogate(2));
+    }
+
+    #[test]
+    fn trailing_content() {
+        let (value, diagnostics) = parse_recovering("[1] 2");
+        assert_eq!(value, array![1]);
+        assert_eq!(diagnostics[0].position.column, 5);
+        let (value, diagnostics) = parse_recovering("]");
+        assert_eq!(value, JsonValue::Null);
+        assert_eq!(diagnostics.len(), 1);
+    }
+}
@@ -0,0 +1,360 @@
+// Fast paths for the tokenizer: skip whitespace, find the end of a string run and
+// validate utf-8 16 (SSE2) or 32 (AVX2) bytes at a time. Every function has a scalar
+// twin and both must agree byte for byte, see the differential tests below.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scanner {
+    Scalar,
+    Sse2,
+    Avx2,
+}
+
+impl Scanner {
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return Scanner::Avx2;
+            }
+            if is_x86_feature_detected!("sse2") {
+                return Scanner::Sse2;
+            }
+        }
+        Scanner::Scalar
+    }
+
+    // index of the first byte at or after `index` that is not json whitespace
+    pub fn skip_whitespace(self, bytes: &[u8], index: usize) -> usize {
+        // most tokens are separated by zero or one space, don't pay for a vector load
+        match bytes.get(index) {
+            Some(b' ' | b'\n' | b'\r' | b'\t') => (),
+            _ => return index,
+        }
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: the variant is only constructed after the feature was detected
+            Scanner::Sse2 => unsafe { x86::skip_whitespace_sse2(bytes, index) },
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: as above
+            Scanner::Avx2 => unsafe { x86::skip_whitespace_avx2(bytes, index) },
+            _ => scalar::skip_whitespace(bytes, index),
+        }
+    }
+
+    // index of the first `"` or `\` at or after `index`, `bytes.len()` if there is none
+    pub fn find_quote_or_escape(self, bytes: &[u8], index: usize) -> usize {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: the variant is only constructed after the feature was detected
+            Scanner::Sse2 => unsafe { x86::find_quote_or_escape_sse2(bytes, index) },
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: as above
+            Scanner::Avx2 => unsafe { x86::find_quote_or_escape_avx2(bytes, index) },
+            _ => scalar::find_quote_or_escape(bytes, index),
+        }
+    }
+
+    // Err(offset of the first invalid byte)
+    pub fn validate_utf8(self, bytes: &[u8]) -> Result<(), usize> {
+        let mut index = 0;
+        while index < bytes.len() {
+            index = self.skip_ascii(bytes, index);
+            if index < bytes.len() {
+                index = scalar::validate_char(bytes, index)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_ascii(self, bytes: &[u8], index: usize) -> usize {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: the variant is only constructed after the feature was detected
+            Scanner::Sse2 => unsafe { x86::skip_ascii_sse2(bytes, index) },
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: as above
+            Scanner::Avx2 => unsafe { x86::skip_ascii_avx2(bytes, index) },
+            _ => scalar::skip_ascii(bytes, index),
+        }
+    }
+}
+
+mod scalar {
+    pub fn skip_whitespace(bytes: &[u8], mut index: usize) -> usize {
+        while let Some(b' ' | b'\n' | b'\r' | b'\t') = bytes.get(index) {
+            index += 1;
+        }
+        index
+    }
+
+    pub fn find_quote_or_escape(bytes: &[u8], index: usize) -> usize {
+        match bytes[index.min(bytes.len())..]
+            .iter()
+            .position(|&ch| ch == b'"' || ch == b'\\')
+        {
+            Some(offset) => index + offset,
+            None => bytes.len(),
+        }
+    }
+
+    pub fn skip_ascii(bytes: &[u8], mut index: usize) -> usize {
+        while index < bytes.len() && bytes[index] < 0x80 {
+            index += 1;
+        }
+        index
+    }
+
+    // validate the multi-byte sequence starting at `index`, return the index after it
+    pub fn validate_char(bytes: &[u8], index: usize) -> Result<usize, usize> {
+        let first = bytes[index];
+        let (width, low, high) = match first {
+            0x00..=0x7F => return Ok(index + 1),
+            0xC2..=0xDF => (2, 0x80, 0xBF),
+            0xE0 => (3, 0xA0, 0xBF),
+            0xE1..=0xEC | 0xEE..=0xEF => (3, 0x80, 0xBF),
+            0xED => (3, 0x80, 0x9F),
+            0xF0 => (4, 0x90, 0xBF),
+            0xF1..=0xF3 => (4, 0x80, 0xBF),
+            0xF4 => (4, 0x80, 0x8F),
+            _ => return Err(index),
+        };
+        match bytes.get(index + 1) {
+            Some(&ch) if (low..=high).contains(&ch) => (),
+            _ => return Err(index),
+        }
+        for i in 2..width {
+            match bytes.get(index + i) {
+                Some(&ch) if (0x80..=0xBF).contains(&ch) => (),
+                _ => return Err(index),
+            }
+        }
+        Ok(index + width)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::scalar;
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn whitespace_mask_sse2(chunk: __m128i) -> u32 {
+        let ws = _mm_or_si128(
+            _mm_or_si128(
+                _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b' ' as i8)),
+                _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'\n' as i8)),
+            ),
+            _mm_or_si128(
+                _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'\r' as i8)),
+                _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'\t' as i8)),
+            ),
+        );
+        _mm_movemask_epi8(ws) as u32
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn skip_whitespace_sse2(bytes: &[u8], mut index: usize) -> usize {
+        while index + 16 <= bytes.len() {
+            let chunk = _mm_loadu_si128(bytes.as_ptr().add(index) as *const __m128i);
+            let other = !whitespace_mask_sse2(chunk) & 0xFFFF;
+            if other != 0 {
+                return index + other.trailing_zeros() as usize;
+            }
+            index += 16;
+        }
+        scalar::skip_whitespace(bytes, index)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn find_quote_or_escape_sse2(bytes: &[u8], mut index: usize) -> usize {
+        while index + 16 <= bytes.len() {
+            let chunk = _mm_loadu_si128(bytes.as_ptr().add(index) as *const __m128i);
+            let special = _mm_or_si128(
+                _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'"' as i8)),
+                _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'\\' as i8)),
+            );
+            let mask = _mm_movemask_epi8(special) as u32;
+            if mask != 0 {
+                return index + mask.trailing_zeros() as usize;
+            }
+            index += 16;
+        }
+        scalar::find_quote_or_escape(bytes, index)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn skip_ascii_sse2(bytes: &[u8], mut index: usize) -> usize {
+        while index + 16 <= bytes.len() {
+            let chunk = _mm_loadu_si128(bytes.as_ptr().add(index) as *const __m128i);
+            let mask = _mm_movemask_epi8(chunk) as u32;
+            if mask != 0 {
+                return index + mask.trailing_zeros() as usize;
+            }
+            index += 16;
+        }
+        scalar::skip_ascii(bytes, index)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn skip_whitespace_avx2(bytes: &[u8], mut index: usize) -> usize {
+        while index + 32 <= bytes.len() {
+            let chunk = _mm256_loadu_si256(bytes.as_ptr().add(index) as *const __m256i);
+            let ws = _mm256_or_si256(
+                _mm256_or_si256(
+                    _mm256_cmpeq_epi8(chunk, _mm256_set1_epi8(b' ' as i8)),
+                    _mm256_cmpeq_epi8(chunk, _mm256_set1_epi8(b'\n' as i8)),
+                ),
+                _mm256_or_si256(
+                    _mm256_cmpeq_epi8(chunk, _mm256_set1_epi8(b'\r' as i8)),
+                    _mm256_cmpeq_epi8(chunk, _mm256_set1_epi8(b'\t' as i8)),
+                ),
+            );
+            let other = !(_mm256_movemask_epi8(ws) as u32);
+            if other != 0 {
+                return index + other.trailing_zeros() as usize;
+            }
+            index += 32;
+        }
+        skip_whitespace_sse2(bytes, index)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn find_quote_or_escape_avx2(bytes: &[u8], mut index: usize) -> usize {
+        while index + 32 <= bytes.len() {
+            let chunk = _mm256_loadu_si256(bytes.as_ptr().add(index) as *const __m256i);
+            let special = _mm256_or_si256(
+                _mm256_cmpeq_epi8(chunk, _mm256_set1_epi8(b'"' as i8)),
+                _mm256_cmpeq_epi8(chunk, _mm256_set1_epi8(b'\\' as i8)),
+            );
+            let mask = _mm256_movemask_epi8(special) as u32;
+            if mask != 0 {
+                return index + mask.trailing_zeros() as usize;
+            }
+            index += 32;
+        }
+        find_quote_or_escape_sse2(bytes, index)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn skip_ascii_avx2(bytes: &[u8], mut index: usize) -> usize {
+        while index + 32 <= bytes.len() {
+            let chunk = _mm256_loadu_si256(bytes.as_ptr().add(index) as *const __m256i);
+            let mask = _mm256_movemask_epi8(chunk) as u32;
+            if mask != 0 {
+                return index + mask.trailing_zeros() as usize;
+            }
+            index += 32;
+        }
+        skip_ascii_sse2(bytes, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+
+    // xorshift, good enough to shake out block boundary bugs
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+            &items[self.next() as usize % items.len()]
+        }
+    }
+
+    fn vector_scanners() -> Vec<Scanner> {
+        let mut scanners = vec![];
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("sse2") {
+                scanners.push(Scanner::Sse2);
+            }
+            if is_x86_feature_detected!("avx2") {
+                scanners.push(Scanner::Avx2);
+            }
+        }
+        scanners
+    }
+
+    fn random_json(rng: &mut Rng) -> String {
+        const PIECES: &[&str] = &[
+            " ", "  \n\t\r   ", "                                  ", "{", "}", "[", "]", ",", ":",
+            "\"", "\\", "\\\"", "\\n", "\\u00e9", "\\ud834\\udd1e", "\\uD834", "abc", "é", "漢字",
+            "𝄞", "true", "fals", "null", "-12.5e3", "0", "\u{7f}", "x",
+        ];
+        let len = rng.next() % 80;
+        (0..len).map(|_| *rng.pick(PIECES)).collect()
+    }
+
+    fn tokens(source: &str, scanner: Scanner) -> Vec<String> {
+        let mut tokenizer = Tokenizer::with_scanner(source, scanner);
+        let mut tokens = vec![];
+        loop {
+            let token = tokenizer.next();
+            tokens.push(format!("{:?}", token));
+            if token.is_err() {
+                return tokens;
+            }
+        }
+    }
+
+    #[test]
+    fn differential_tokens() {
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+        for _ in 0..20_000 {
+            let source = random_json(&mut rng);
+            let expected = tokens(&source, Scanner::Scalar);
+            for scanner in vector_scanners() {
+                assert_eq!(tokens(&source, scanner), expected, "{:?} on {:?}", scanner, source);
+            }
+        }
+    }
+
+    #[test]
+    fn differential_scans() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..20_000 {
+            let len = (rng.next() % 100) as usize;
+            let bytes: Vec<u8> = (0..len).map(|_| *rng.pick(b" \t\n\r\"\\ax\x80\xC3\xA9\xF0")).collect();
+            let start = (rng.next() as usize) % (len + 1);
+            for scanner in vector_scanners() {
+                assert_eq!(
+                    scanner.skip_whitespace(&bytes, start),
+                    Scanner::Scalar.skip_whitespace(&bytes, start)
+                );
+                assert_eq!(
+                    scanner.find_quote_or_escape(&bytes, start),
+                    Scanner::Scalar.find_quote_or_escape(&bytes, start)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn differential_utf8() {
+        let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+        for _ in 0..20_000 {
+            let len = (rng.next() % 100) as usize;
+            let mut bytes: Vec<u8> = "a漢é𝄞bc".repeat(len / 8 + 1).into_bytes();
+            // corrupt a few bytes so that most inputs hit an error somewhere
+            for _ in 0..rng.next() % 3 {
+                let at = rng.next() as usize % bytes.len();
+                bytes[at] = rng.next() as u8;
+            }
+            let expected = std::str::from_utf8(&bytes).map(|_| ()).map_err(|e| e.valid_up_to());
+            assert_eq!(Scanner::Scalar.validate_utf8(&bytes), expected);
+            for scanner in vector_scanners() {
+                assert_eq!(scanner.validate_utf8(&bytes), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{error::JsonError, parser::Parser, value::JsonValue, JsonResult};
+
+// RFC 7464 record separator
+const RS: u8 = 0x1E;
+
+fn is_whitespace(ch: u8) -> bool {
+    matches!(ch, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+fn skip_whitespace(source: &str, offset: usize) -> usize {
+    let bytes = source.as_bytes();
+    (offset..bytes.len())
+        .find(|&i| !is_whitespace(bytes[i]))
+        .unwrap_or(bytes.len())
+}
+
+enum Framing<'a> {
+    // values back to back, `{..}{..}[..]` or `1 2 3`, read off one parser
+    Concatenated(Parser<'a>),
+    // `RS value LF` records, RFC 7464, each parsed on its own
+    TextSequence,
+}
+
+// Iterates over the values of a multi-value document, each one paired with the
+// byte offset it starts at.
+pub struct JsonStream<'a> {
+    source: &'a str,
+    framing: Framing<'a>,
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> JsonStream<'a> {
+    // Concatenated values, separated by optional whitespace. The first error ends
+    // the stream, there is no telling where the next value would start.
+    pub fn new(source: &'a str) -> Self {
+        Self::with_framing(source, Framing::Concatenated(Parser::new(source)))
+    }
+
+    // An RFC 7464 json text sequence. A record that fails to parse is reported and
+    // reading resumes at the next RS. A top level number, `true`, `false` or `null`
+    // without whitespace after it may have been truncated and is reported as
+    // `UnexpectedEndOfJson`. Empty records are ignored.
+    pub fn text_sequence(source: &'a str) -> Self {
+        Self::with_framing(source, Framing::TextSequence)
+    }
+
+    fn with_framing(source: &'a str, framing: Framing<'a>) -> Self {
+        JsonStream {
+            source,
+            framing,
+            offset: 0,
+            done: false,
+        }
+    }
+
+    // where reading the next value starts
+    pub fn byte_offset(&self) -> usize {
+        self.offset
+    }
+
+    // off the one parser, or out of the next record
+    fn next_value(&mut self) -> Option<(usize, JsonResult<JsonValue>)> {
+        let Framing::Concatenated(parser) = &mut self.framing else {
+            return self.next_record();
+        };
+        let start = skip_whitespace(self.source, self.offset);
+        if start == self.source.len() {
+            return None;
+        }
+        let value = parser.value();
+        self.offset = parser.offset();
+        if value.is_err() {
+            self.done = true;
+        }
+        Some((start, value))
+    }
+
+    fn next_record(&mut self) -> Option<(usize, JsonResult<JsonValue>)> {
+        let bytes = self.source.as_bytes();
+        loop {
+            // skip the separator, records without one (leading text) are read as is
+            if bytes.get(self.offset) == Some(&RS) {
+                self.offset += 1;
+            }
+            let end = (self.offset..bytes.len())
+                .find(|&i| bytes[i] == RS)
+                .unwrap_or(bytes.len());
+            let (record_start, record) = (self.offset, &self.source[self.offset..end]);
+            let start = skip_whitespace(self.source, self.offset);
+            self.offset = end;
+
+            if start < end {
+                // offsets in the input, not in the record
+                let value = parse_record(record).map_err(|e| e.map_offset(|o| o + record_start));
+                return Some((start, value));
+            }
+            if end == bytes.len() {
+                return None;
+            }
+        }
+    }
+}
+
+fn parse_record(record: &str) -> JsonResult<JsonValue> {
+    let mut parser = Parser::new(record);
+    let value = parser.value()?;
+    let end = parser.offset();
+    match parser.consume() {
+        Err(JsonError::UnexpectedEndOfJson) => (),
+        Ok(token) => return Err(parser.unexpected(token)),
+        Err(err) => return Err(err),
+    }
+    let self_delimited = matches!(
+        value,
+        JsonValue::String(_) | JsonValue::Wtf8(_) | JsonValue::Array(_) | JsonValue::Object(_)
+    );
+    if !self_delimited && !record[end..].bytes().next().is_some_and(is_whitespace) {
+        return Err(JsonError::UnexpectedEndOfJson);
+    }
+    Ok(value)
+}
+
+impl Iterator for JsonStream<'_> {
+    type Item = (usize, JsonResult<JsonValue>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_value();
+        if next.is_none() {
+            self.done = true;
+        }
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, object};
+
+    #[test]
+    fn concatenated() {
+        let s = r#"{"a":1}{"b":2} [3]"x"4 5"#;
+        let values: Vec<_> = JsonStream::new(s).map(|(at, v)| (at, v.unwrap())).collect();
+        assert_eq!(
+            values,
+            vec![
+                (0, object! {"a" => 1}),
+                (7, object! {"b" => 2}),
+                (15, array![3]),
+                (18, "x".into()),
+                (21, 4.into()),
+                (23, 5.into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn concatenated_stops_at_error() {
+        let mut stream = JsonStream::new("[1] [2,] [3]");
+        assert_eq!(stream.next(), Some((0, Ok(array![1]))));
+        assert_eq!(stream.byte_offset(), 3);
+        let (at, err) = stream.next().unwrap();
+        assert_eq!(at, 4);
+        assert!(err.unwrap_err().is_syntax());
+        assert_eq!(stream.next(), None);
+        assert_eq!(JsonStream::new("  \n").next(), None);
+    }
+
+    #[test]
+    fn text_sequence() {
+        let s = "\x1e{\"a\":1}\n\x1e\x1e 2\n\x1e[1,\x1e3\x1e\"s\"\x1etrue\n\x1e4 5\n";
+        let items: Vec<_> = JsonStream::text_sequence(s).collect();
+        assert_eq!(items.len(), 7);
+        assert_eq!(items[0], (1, Ok(object! {"a" => 1})));
+        assert_eq!(items[1], (12, Ok(2.into())));
+        // truncated array, resumes at the next record
+        assert_eq!(items[2], (15, Err(JsonError::UnexpectedEndOfJson)));
+        // a number with no whitespace after it may be truncated too
+        assert_eq!(items[3], (19, Err(JsonError::UnexpectedEndOfJson)));
+        assert_eq!(items[4], (21, Ok("s".into())));
+        assert_eq!(items[5], (25, Ok(true.into())));
+        assert!(items[6].1.as_ref().unwrap_err().is_syntax());
+
+        // error offsets count from the start of the input
+        let items: Vec<_> = JsonStream::text_sequence("\x1e[1]\n\x1e[2 x]\n").collect();
+        assert_eq!(items[0], (1, Ok(array![1])));
+        assert_eq!(items[1], (6, Err(JsonError::UnexpectedCharacter('x', 9))));
+        assert_eq!(items[1].1.as_ref().unwrap_err().offset(), Some(9));
+    }
+}
@@ -1,39 +1,107 @@
-use std::{char::decode_utf16, iter::Peekable, str::Bytes};
+use std::{borrow::Cow, char::decode_utf16, mem};
 
-use crate::{error::JsonError, JsonResult};
+use crate::{
+    error::JsonError,
+    number::Number,
+    parser::SurrogatePolicy,
+    scan::Scanner,
+    value::JsonValue,
+    wtf8::{encode_surrogate, Wtf8String},
+    JsonResult,
+};
 
 #[derive(Debug, PartialEq)]
-pub enum Token {
+pub enum Token<'a> {
     Comma,          // ,
     Colon,          // :
     BracketOn,      // [
     BracketOff,     // ]
     BraceOn,        // {
     BraceOff,       // }
-    String(String), // "string"
-    Number(f64),    // 123
+    String(Cow<'a, str>), // "string", borrowed from the source unless it has escapes
+    Wtf8(Wtf8String), // "\ud800", a string with lone surrogates under `SurrogatePolicy::Preserve`
+    Number(Number), // 123
     Boolen(bool),   // "true/false"
     Null,           // "null"
 }
+
+// the token as it would be written in json, strings and numbers normalized
+impl std::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Comma => f.write_str(","),
+            Token::Colon => f.write_str(":"),
+            Token::BracketOn => f.write_str("["),
+            Token::BracketOff => f.write_str("]"),
+            Token::BraceOn => f.write_str("{"),
+            Token::BraceOff => f.write_str("}"),
+            Token::String(s) => f.write_str(&JsonValue::String(s.to_string()).dump()),
+            Token::Wtf8(s) => f.write_str(&JsonValue::Wtf8(s.clone()).dump()),
+            Token::Number(n) => f.write_str(&JsonValue::Number(*n).dump()),
+            Token::Boolen(b) => write!(f, "{}", b),
+            Token::Null => f.write_str("null"),
+        }
+    }
+}
 pub struct Tokenizer<'a> {
-    source: Peekable<Bytes<'a>>,
+    source: &'a str,
+    index: usize,
+    start: usize,
     buffer: Vec<u8>,
+    scanner: Scanner,
+    surrogates: SurrogatePolicy,
+    // a lone surrogate went into the buffer, it is WTF-8 rather than utf-8
+    wtf8: bool,
 }
 
 impl<'a> Tokenizer<'a> {
     pub fn new(source: &'a str) -> Self {
+        Self::with_scanner(source, Scanner::detect())
+    }
+
+    pub(crate) fn with_scanner(source: &'a str, scanner: Scanner) -> Self {
         Self {
-            source: source.bytes().peekable(),
+            source,
+            index: 0,
+            start: 0,
             buffer: Vec::new(),
+            scanner,
+            surrogates: SurrogatePolicy::Error,
+            wtf8: false,
+        }
+    }
+
+    pub fn with_surrogates(source: &'a str, surrogates: SurrogatePolicy) -> Self {
+        Tokenizer {
+            surrogates,
+            ..Self::new(source)
         }
     }
 
+    // byte offset of the next unread byte
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    // byte offset where the last token (or the error returned instead) started
+    pub fn token_start(&self) -> usize {
+        self.start
+    }
+
+    fn peek_byte(&self) -> Option<u8> {
+        self.source.as_bytes().get(self.index).copied()
+    }
+
     fn next_byte(&mut self) -> JsonResult<u8> {
-        self.source.next().ok_or(JsonError::UnexpectedEndOfJson)
+        let ch = self.peek_byte().ok_or(JsonError::UnexpectedEndOfJson)?;
+        self.index += 1;
+        Ok(ch)
     }
 
-    pub fn next(&mut self) -> JsonResult<Token> {
+    pub fn next(&mut self) -> JsonResult<Token<'a>> {
         loop {
+            self.index = self.scanner.skip_whitespace(self.source.as_bytes(), self.index);
+            self.start = self.index;
             let chr = self.next_byte()?;
             return Ok(match chr {
                 b',' => Token::Comma,
@@ -46,53 +114,82 @@ impl<'a> Tokenizer<'a> {
                 b'0'..=b'9' | b'-' => self.read_number(chr)?,
                 b'"' => self.read_string()?,
                 0x0A | 0x0D | 0x20 | 0x09 => continue, // whitespace '0020' ws '000A' ws '000D' ws '0009' ws
-                _ => return Err(JsonError::unexpected_character(chr)),
+                _ => return Err(JsonError::unexpected_character(chr, self.start)),
             });
         }
     }
 
-    fn read_ident(&mut self, ch: u8) -> JsonResult<Token> {
+    fn read_ident(&mut self, ch: u8) -> JsonResult<Token<'a>> {
         match ch {
             b'n' => self.expect_str(b"ull", Token::Null),
             b't' => self.expect_str(b"rue", Token::Boolen(true)),
             b'f' => self.expect_str(b"alse", Token::Boolen(false)),
-            _ => return Err(JsonError::unexpected_character(ch)),
+            _ => Err(JsonError::unexpected_character(ch, self.start)),
         }
     }
 
+    // the offending byte is left unread, so `tru,` resumes at the comma
     fn expect_str<T>(&mut self, str: &[u8], token: T) -> JsonResult<T> {
         for &espect in str {
-            let ch = self.next_byte()?;
+            let ch = self.peek_byte().ok_or(JsonError::UnexpectedEndOfJson)?;
             if ch != espect {
-                return Err(JsonError::unexpected_character(ch));
+                return Err(JsonError::unexpected_character(ch, self.index));
             }
+            self.index += 1;
         }
         Ok(token)
     }
 
-    fn expect_next_str(&mut self, str: &[u8]) -> JsonResult<()> {
-        for &espect in str {
-            let ch = self.next_byte()?;
-            if ch != espect {
-                return Err(JsonError::unexpected_character(ch));
-            }
+    // "abc" borrows from the source, only strings with escapes are copied into the buffer
+    fn read_string(&mut self) -> JsonResult<Token<'a>> {
+        let bytes = self.source.as_bytes();
+        let start = self.index;
+        self.index = self.scanner.find_quote_or_escape(bytes, self.index);
+        if self.next_byte()? == b'"' {
+            return Ok(Token::String(Cow::Borrowed(&self.source[start..self.index - 1])));
         }
-        Ok(())
-    }
 
-    fn read_string(&mut self) -> JsonResult<Token> {
         self.buffer.clear();
+        self.wtf8 = false;
+        self.buffer.extend_from_slice(&bytes[start..self.index - 1]);
+        self.read_escaped_chr()?;
         loop {
-            let ch = self.next_byte()?;
-            match ch {
+            let run = self.index;
+            self.index = self.scanner.find_quote_or_escape(bytes, self.index);
+            self.buffer.extend_from_slice(&bytes[run..self.index]);
+            match self.next_byte()? {
                 b'"' => break,
-                b'\\' => self.read_escaped_chr()?,
-                _ => self.buffer.push(ch),
+                _ => self.read_escaped_chr()?,
             }
         }
-        match String::from_utf8(self.buffer.clone()) {
-            Ok(s) => Ok(Token::String(s)),
-            Err(e) => return Err(JsonError::ParsingFailed(e.to_string())),
+        let buffer = mem::take(&mut self.buffer);
+        if self.wtf8 {
+            return Ok(Token::Wtf8(Wtf8String::from_wtf8_unchecked(buffer)));
+        }
+        // SAFETY: without a lone surrogate the buffer holds runs of `source` cut at
+        // ascii quotes and backslashes, the byte after a backslash with the run that
+        // follows it, and utf-8 encoded chars, so it is utf-8
+        Ok(Token::String(Cow::Owned(unsafe { String::from_utf8_unchecked(buffer) })))
+    }
+
+    // After an error inside a string, move past its closing quote so reading can go
+    // on after it. False when the failed token wasn't a string.
+    pub(crate) fn skip_string(&mut self) -> bool {
+        let bytes = self.source.as_bytes();
+        if bytes.get(self.start) != Some(&b'"') {
+            return false;
+        }
+        self.index = self.start + 1;
+        loop {
+            self.index = self.scanner.find_quote_or_escape(bytes, self.index);
+            match bytes.get(self.index) {
+                Some(b'"') => {
+                    self.index += 1;
+                    return true;
+                }
+                Some(_) => self.index = (self.index + 2).min(bytes.len()),
+                None => return true,
+            }
         }
     }
 
@@ -113,43 +210,59 @@ impl<'a> Tokenizer<'a> {
         Ok(())
     }
 
+    // \uXXXX, a high surrogate pairs with a low one in the escape right after it
     fn read_unicode(&mut self) -> JsonResult<()> {
-        let codepoint = self.read_hex_codepoint()?;
-
-        let unicode = match char::try_from(codepoint as u32) {
-            Ok(code) => code,
-            Err(_) => {
-                self.expect_next_str(b"\\u")?;
-                let next_codepoint = self.read_hex_codepoint()?;
-                // println!("codepoint = {:x}", codepoint);
-                // println!("next_codepoint = {:x}", next_codepoint);
-                match decode_utf16([codepoint, next_codepoint].iter().cloned()).next() {
-                    Some(Ok(code)) => code,
-                    _ => return Err(JsonError::parsing_faild("parsing unicode error")),
+        // offset of the backslash
+        let escape = self.index - 2;
+        let unit = self.read_hex_codepoint(escape)?;
+
+        let ch = match unit {
+            0xD800..=0xDBFF if self.source.as_bytes()[self.index..].starts_with(b"\\u") => {
+                let next = self.index;
+                self.index += 2;
+                let low = self.read_hex_codepoint(next)?;
+                match decode_utf16([unit, low]).next() {
+                    Some(Ok(ch)) => ch,
+                    // not a pair, the second escape is read on its own
+                    _ => {
+                        self.index = next;
+                        return self.lone_surrogate(unit, escape);
+                    }
                 }
             }
+            0xD800..=0xDFFF => return self.lone_surrogate(unit, escape),
+            _ => char::from_u32(unit as u32).unwrap(),
         };
         self.buffer
-            .extend_from_slice(unicode.encode_utf8(&mut [0_u8; 4]).as_bytes());
+            .extend_from_slice(ch.encode_utf8(&mut [0_u8; 4]).as_bytes());
 
         Ok(())
     }
 
-    fn read_hex_codepoint(&mut self) -> JsonResult<u16> {
-        Ok(self.read_hexdec_digit()? << 12
-            | self.read_hexdec_digit()? << 8
-            | self.read_hexdec_digit()? << 4
-            | self.read_hexdec_digit()?)
+    fn lone_surrogate(&mut self, unit: u16, escape: usize) -> JsonResult<()> {
+        match self.surrogates {
+            SurrogatePolicy::Error => return Err(JsonError::UnpairedSurrogate(escape)),
+            SurrogatePolicy::Replace => self.buffer.extend_from_slice("\u{FFFD}".as_bytes()),
+            SurrogatePolicy::Preserve => {
+                self.buffer.extend_from_slice(&encode_surrogate(unit));
+                self.wtf8 = true;
+            }
+        }
+        Ok(())
     }
 
-    fn read_hexdec_digit(&mut self) -> JsonResult<u16> {
-        let ch = self.next_byte()?;
-        Ok(match ch {
-            b'0'..=b'9' => (ch - b'0'),
-            b'a'..=b'f' => (ch + 10 - b'a'),
-            b'A'..=b'F' => (ch + 10 - b'A'),
-            val => return Err(JsonError::unexpected_character(val)),
-        } as u16)
+    fn read_hex_codepoint(&mut self, escape: usize) -> JsonResult<u16> {
+        let mut unit = 0;
+        for _ in 0..4 {
+            let digit = match self.next_byte()? {
+                ch @ b'0'..=b'9' => ch - b'0',
+                ch @ b'a'..=b'f' => ch + 10 - b'a',
+                ch @ b'A'..=b'F' => ch + 10 - b'A',
+                _ => return Err(JsonError::InvalidUnicodeEscape(escape)),
+            };
+            unit = unit << 4 | digit as u16;
+        }
+        Ok(unit)
     }
 
     // fn read_codepoint(&mut self) {
@@ -168,32 +281,76 @@ impl<'a> Tokenizer<'a> {
     // fn read_hex(&mut self) -> JsonResult<u32> {
     //     let ch = self.next_byte()?;
     //     Ok(match ch {
-    //         b'0'..=b'9' => (ch - b'0'),
-    //         b'a'..=b'f' => (ch + 10 - b'a'),
-    //         b'A'..=b'F' => (ch + 10 - b'A'),
+    //         b'0'..=b'9' => ch - b'0',
+    //         b'a'..=b'f' => ch + 10 - b'a',
+    //         b'A'..=b'F' => ch + 10 - b'A',
     //         ch => return Err(JsonError::unexpected_character(ch)),
     //     } as u32)
     // }
 
-    fn read_number(&mut self, chr: u8) -> JsonResult<Token> {
-        self.buffer.clear();
-        self.buffer.push(chr);
-        while let Some(&ch) = self.source.peek() {
+    // integers are accumulated while scanning, floats and the rare overlong
+    // or malformed integer are parsed from the source slice without copying
+    fn read_number(&mut self, chr: u8) -> JsonResult<Token<'a>> {
+        let start = self.index - 1;
+        let negative = chr == b'-';
+        let mut value: u64 = if negative { 0 } else { (chr - b'0') as u64 };
+        let mut is_float = false;
+        let mut simple = true;
+        while let Some(ch) = self.peek_byte() {
             match ch {
-                b'0'..=b'9' => self.buffer.push(ch),
-                b'.' => self.buffer.push(ch),
-                b'e' | b'E' => self.buffer.push(ch),
-                b'+' | b'-' => self.buffer.push(ch),
+                b'0'..=b'9' => {
+                    match value.checked_mul(10).and_then(|v| v.checked_add((ch - b'0') as u64)) {
+                        Some(v) => value = v,
+                        None => simple = false,
+                    }
+                }
+                b'.' | b'e' | b'E' => is_float = true,
+                b'+' | b'-' => simple = false,
                 _ => break,
             }
-            self.next_byte()?;
+            self.index += 1;
+        }
+        let s = &self.source[start..self.index];
+        // "-0" has no integer representation, keep its sign as a float
+        if !is_float && s != "-0" {
+            if simple && s != "-" {
+                let n = value as i128;
+                return Ok(Token::Number(Number::from(if negative { -n } else { n })));
+            }
+            if let Ok(n) = s.parse::<i128>() {
+                return Ok(Token::Number(Number::from(n)));
+            }
         }
-        let s = String::from_utf8(self.buffer.clone()).unwrap();
         match s.parse::<f64>() {
-            Ok(n) => Ok(Token::Number(n)),
-            Err(_e) => return Err(JsonError::InvalidNumber),
+            Ok(n) => Ok(Token::Number(Number::Float(n))),
+            // `-`, `1e` or `1.5e+` cut off by the end of the input could still go on
+            Err(_) if self.index == self.source.len() && cut_short(s) => {
+                Err(JsonError::UnexpectedEndOfJson)
+            }
+            Err(_) => Err(JsonError::InvalidNumber(start)),
+        }
+    }
+}
+
+// `s` ends where a number needs more digits: after the sign, the dot or the exponent
+fn cut_short(s: &str) -> bool {
+    let mut bytes = s.bytes().peekable();
+    bytes.next_if_eq(&b'-');
+    if bytes.peek().is_none() {
+        return true;
+    }
+    while bytes.next_if(u8::is_ascii_digit).is_some() {}
+    if bytes.next_if_eq(&b'.').is_some() {
+        if bytes.peek().is_none() {
+            return true;
         }
+        while bytes.next_if(u8::is_ascii_digit).is_some() {}
     }
+    if bytes.next_if(|&b| b == b'e' || b == b'E').is_some() {
+        bytes.next_if(|&b| b == b'+' || b == b'-');
+        return bytes.peek().is_none();
+    }
+    false
 }
 
 #[cfg(test)]
@@ -224,6 +381,12 @@ mod test {
         let s = r#""abc\r\n\t\b\f\\\"""#;
         let mut de = Tokenizer::new(s);
         println!("{:?}", de.next());
+
+        // escaped strings are taken out of the buffer, which starts over for the next one
+        let mut tokens = Tokenizer::new(r#""a\né" "\éé" "x""#);
+        assert_eq!(tokens.next(), Ok(Token::String("a\né".into())));
+        assert_eq!(tokens.next(), Ok(Token::String("éé".into())));
+        assert_eq!(tokens.next(), Ok(Token::String(Cow::Borrowed("x"))));
     }
 
     #[test]
@@ -231,31 +394,55 @@ mod test {
         // println!("{:?}", ret);
         assert_eq!(
             Tokenizer::new(r#" 1234 "#).next().unwrap(),
-            Token::Number(1234.0)
+            Token::Number(1234.into())
         );
         assert_eq!(
             Tokenizer::new(r#" -1234 "#).next().unwrap(),
-            Token::Number(-1234.0)
+            Token::Number((-1234).into())
         );
         assert_eq!(
             Tokenizer::new(r#"   -1.23E4 "#).next().unwrap(),
-            Token::Number(-12300.0)
+            Token::Number((-12300.0).into())
         );
         assert_eq!(
             Tokenizer::new("1.23e4").next().unwrap(),
-            Token::Number(12300.0)
+            Token::Number(12300.0.into())
         );
         assert_eq!(
             Tokenizer::new("-1.23e-4").next().unwrap(),
-            Token::Number(-0.000123)
+            Token::Number((-0.000123).into())
         );
         assert_eq!(
             Tokenizer::new("-1.23e+4").next().unwrap(),
-            Token::Number(-12300.0)
+            Token::Number((-12300.0).into())
         );
         assert_eq!(
             Tokenizer::new(r#"   -1.23e"#).next().err().unwrap(),
-            JsonError::InvalidNumber
+            JsonError::UnexpectedEndOfJson
+        );
+        assert_eq!(
+            Tokenizer::new(r#"   -1.23e,"#).next().err().unwrap(),
+            JsonError::InvalidNumber(3)
+        );
+    }
+
+    #[test]
+    fn read_long_number() {
+        assert_eq!(
+            Tokenizer::new("-18446744073709551616").next().unwrap(),
+            Token::Number(Number::from(-18446744073709551616i128))
+        );
+        assert_eq!(
+            Tokenizer::new("1-2").next().err().unwrap(),
+            JsonError::InvalidNumber(0)
+        );
+        assert_eq!(
+            Tokenizer::new("-").next().err().unwrap(),
+            JsonError::UnexpectedEndOfJson
+        );
+        assert_eq!(
+            Tokenizer::new("1-").next().err().unwrap(),
+            JsonError::InvalidNumber(0)
         );
     }
 
@@ -1,16 +1,30 @@
-use std::{collections::BTreeMap, fmt::Display};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
 
-use crate::generator::Generator;
+use crate::{
+    canonical::to_canonical_string, generator::Generator, number::Number,
+    parser::parse_complete, wtf8::Wtf8String, JsonResult,
+};
 
 
-#[derive(Debug, PartialEq)]
+// Eq, Ord and Hash follow `Number`'s structural semantics. Values of different types
+// order as the variants are declared: null < booleans < strings < numbers < arrays < objects
+// < raw.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum JsonValue {
     Null,
     Boolen(bool),
     String(String),
-    Number(f64),
+    // a string with lone surrogates, only from `SurrogatePolicy::Preserve`
+    Wtf8(Wtf8String),
+    Number(Number),
     Array(Vec<JsonValue>),
     Object(BTreeMap<String, JsonValue>),
+    // json text that is written out byte for byte, never reparsed or reformatted. Only
+    // `JsonValue::raw` checks that it is valid.
+    Raw(String),
 }
 
 impl JsonValue {
@@ -19,12 +33,104 @@ impl JsonValue {
         gen.write_json(self);
         gen.value()
     }
+
+    pub fn pretty(&self, spaces: u8) -> String {
+        let mut gen = Generator::new(false, spaces);
+        gen.write_json(self);
+        gen.value()
+    }
+
+    // RFC 6901 pointer, "" is the whole document and "/a/0" the first item of "a"
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        pointer_tokens(pointer)?.try_fold(self, |value, token| match value {
+            JsonValue::Object(object) => object.get(&token),
+            JsonValue::Array(array) => array.get(pointer_index(&token)?),
+            _ => None,
+        })
+    }
+
+    // FNV-1a over the RFC 8785 canonical form, so it is stable across runs, platforms
+    // and key order, and `1`, `1.0` and `1e0` hash alike. Fails where canonical form does.
+    pub fn content_hash(&self) -> JsonResult<u64> {
+        let canonical = to_canonical_string(self)?;
+        Ok(canonical.bytes().fold(0xcbf29ce484222325, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        }))
+    }
+
+    // like `==` but numbers compare by value: `1`, `1.0` and `1e0` are equal, so are
+    // `0` and `-0.0`, and NaN equals nothing
+    pub fn semantic_eq(&self, other: &JsonValue) -> bool {
+        match (self, other) {
+            (JsonValue::Number(a), JsonValue::Number(b)) => a.value_eq(b),
+            (JsonValue::Array(a), JsonValue::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.semantic_eq(b))
+            }
+            (JsonValue::Object(a), JsonValue::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter().zip(b).all(|((ka, a), (kb, b))| ka == kb && a.semantic_eq(b))
+            }
+            _ => self == other,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            JsonValue::Null => "null",
+            JsonValue::Boolen(_) => "boolean",
+            JsonValue::String(_) | JsonValue::Wtf8(_) => "string",
+            JsonValue::Number(_) => "number",
+            JsonValue::Array(_) => "array",
+            JsonValue::Object(_) => "object",
+            JsonValue::Raw(_) => "raw",
+        }
+    }
+
+    // `JsonValue::Raw`, once `json` is checked to be a single json value. Whitespace
+    // around it is trimmed, the rest is written out as it is.
+    pub fn raw(json: impl Into<String>) -> JsonResult<JsonValue> {
+        let mut json = json.into();
+        parse_complete(&json)?;
+        let trimmed = json.trim_matches([' ', '\t', '\n', '\r']);
+        if trimmed.len() != json.len() {
+            json = trimmed.to_string();
+        }
+        Ok(JsonValue::Raw(json))
+    }
+}
+
+// a key as one RFC 6901 reference token
+pub(crate) fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+// the unescaped reference tokens of an RFC 6901 pointer, none for "", `None` unless it
+// is empty or starts with `/`
+pub(crate) fn pointer_tokens(pointer: &str) -> Option<impl Iterator<Item = String> + '_> {
+    let path = match pointer {
+        "" => None,
+        pointer => Some(pointer.strip_prefix('/')?),
+    };
+    let tokens = path.into_iter().flat_map(|path| path.split('/'));
+    Some(tokens.map(|token| token.replace("~1", "/").replace("~0", "~")))
+}
+
+// a reference token as an array index: ascii digits, no leading zero
+pub(crate) fn pointer_index(token: &str) -> Option<usize> {
+    if token.is_empty() || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if token.len() > 1 && token.starts_with('0') {
+        return None;
+    }
+    token.parse().ok()
 }
 
 impl Display for JsonValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
             JsonValue::String(ref value)  => value.fmt(f),
+            JsonValue::Wtf8(ref value)    => value.fmt(f),
             JsonValue::Number(ref value)  => value.fmt(f),
             JsonValue::Boolen(ref value) => value.fmt(f),
             JsonValue::Null               => f.write_str("null"),
@@ -33,15 +139,48 @@ impl Display for JsonValue {
     }
 }
 
-impl From<Vec<JsonValue>> for JsonValue {
-    fn from(v: Vec<JsonValue>) -> Self {
-        JsonValue::Array(v)
+impl<T: Into<JsonValue>> From<Vec<T>> for JsonValue {
+    fn from(v: Vec<T>) -> Self {
+        JsonValue::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<'a, T: Clone + Into<JsonValue>> From<&'a [T]> for JsonValue {
+    fn from(v: &'a [T]) -> Self {
+        JsonValue::Array(v.iter().cloned().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<JsonValue>, const N: usize> From<[T; N]> for JsonValue {
+    fn from(v: [T; N]) -> Self {
+        JsonValue::Array(v.into_iter().map(Into::into).collect())
     }
 }
 
-impl From<BTreeMap<String, JsonValue>> for JsonValue {
-    fn from(val: BTreeMap<String, JsonValue>) -> JsonValue {
-        JsonValue::Object(val)
+impl<T: Into<JsonValue>> FromIterator<T> for JsonValue {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        JsonValue::Array(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<String>, V: Into<JsonValue>> From<BTreeMap<K, V>> for JsonValue {
+    fn from(val: BTreeMap<K, V>) -> JsonValue {
+        JsonValue::Object(val.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+impl<K: Into<String>, V: Into<JsonValue>, S> From<HashMap<K, V, S>> for JsonValue {
+    fn from(val: HashMap<K, V, S>) -> JsonValue {
+        JsonValue::Object(val.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(val: Option<T>) -> JsonValue {
+        match val {
+            Some(value) => value.into(),
+            None => JsonValue::Null,
+        }
     }
 }
 
@@ -51,18 +190,41 @@ impl<'a> From<&'a str> for JsonValue {
     }
 }
 
+impl<'a> From<&'a String> for JsonValue {
+    fn from(s: &'a String) -> Self {
+        JsonValue::String(s.clone())
+    }
+}
+
+impl From<char> for JsonValue {
+    fn from(c: char) -> Self {
+        JsonValue::String(c.to_string())
+    }
+}
+
 macro_rules! impl_from_num_for_json {
     ($($t: ident), *) => {
       $(
         impl From<$t> for JsonValue {
             fn from(value: $t) -> JsonValue {
-                JsonValue::Number(value as f64)
+                JsonValue::Number(value.into())
             }
         }
       )*
     };
   }
 
+macro_rules! impl_from_tuple_for_json {
+    ($($t: ident), *) => {
+        impl<$($t: Into<JsonValue>), *> From<($($t,)*)> for JsonValue {
+            #[allow(non_snake_case)]
+            fn from(($($t,)*): ($($t,)*)) -> JsonValue {
+                JsonValue::Array(vec![$($t.into()), *])
+            }
+        }
+    };
+}
+
 macro_rules! implement {
     ($from:ty, $to:ident) => {
         impl From<$from> for JsonValue {
@@ -73,6 +235,120 @@ macro_rules! implement {
     };
 }
 
-impl_from_num_for_json!(i8, i16, i32, i64, isize);
+impl_from_num_for_json!(i8, i16, i32, i64, i128, isize);
+impl_from_num_for_json!(u8, u16, u32, u64, u128, usize);
+impl_from_num_for_json!(f32, f64);
+impl_from_tuple_for_json!(A);
+impl_from_tuple_for_json!(A, B);
+impl_from_tuple_for_json!(A, B, C);
+impl_from_tuple_for_json!(A, B, C, D);
+impl_from_tuple_for_json!(A, B, C, D, E);
+impl_from_tuple_for_json!(A, B, C, D, E, F);
+impl_from_tuple_for_json!(A, B, C, D, E, F, G);
+impl_from_tuple_for_json!(A, B, C, D, E, F, G, H);
 implement!(bool, Boolen);
 implement!(String, String);
+implement!(Wtf8String, Wtf8);
+implement!(Number, Number);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, object};
+
+    #[test]
+    fn from_numbers() {
+        assert_eq!(JsonValue::from(u64::MAX), JsonValue::Number(Number::Unsigned(u64::MAX)));
+        assert_eq!(JsonValue::from(i128::MAX).dump(), i128::MAX.to_string());
+        assert_eq!(JsonValue::from(2.5f32), JsonValue::Number(Number::Float(2.5)));
+        assert_eq!(array![1u8, 2usize, 3.5], array![1, 2, 3.5f64]);
+    }
+
+    #[test]
+    fn from_std_types() {
+        let name = String::from("abc");
+        let none: Option<i32> = None;
+        let obj = object! {"name" => &name, "age" => Some(12), "phone" => none, "initial" => 'a'};
+        assert_eq!(obj.dump(), r#"{"age":12,"initial":"a","name":"abc","phone":null}"#);
+
+        let mut map = HashMap::new();
+        map.insert("a", vec![1, 2]);
+        assert_eq!(JsonValue::from(map).dump(), r#"{"a":[1,2]}"#);
+
+        let slice: &[&str] = &["x", "y"];
+        assert_eq!(JsonValue::from(slice), array!["x", "y"]);
+        assert_eq!(JsonValue::from([true, false]), array![true, false]);
+        assert_eq!(JsonValue::from((1, "a", JsonValue::Null)), array![1, "a", JsonValue::Null]);
+    }
+
+    #[test]
+    fn from_iterator() {
+        let arr: JsonValue = (1..4).collect();
+        assert_eq!(arr, array![1, 2, 3]);
+        let arr: JsonValue = ["a", "b"].iter().map(|s| s.to_uppercase()).collect();
+        assert_eq!(arr, array!["A", "B"]);
+    }
+
+    #[test]
+    fn pointer() {
+        let value = object! {"a" => array![1, object! {"b/c" => true, "~" => 2}], "" => 3};
+        assert_eq!(value.pointer(""), Some(&value));
+        assert_eq!(value.pointer("/a/1/b~1c"), Some(&JsonValue::Boolen(true)));
+        assert_eq!(value.pointer("/a/1/~0"), Some(&2.into()));
+        assert_eq!(value.pointer("/"), Some(&3.into()));
+        assert_eq!(value.pointer("/a/01"), None);
+        assert_eq!(value.pointer("/a/+1"), None);
+        assert_eq!(value.pointer("/a/2"), None);
+        assert_eq!(value.pointer("a"), None);
+    }
+
+    #[test]
+    fn hash_and_order() {
+        use std::collections::HashSet;
+
+        let nan = JsonValue::from(f64::NAN);
+        let values = [array![1, "a"], array![1, "a"], array![1.0, "a"], nan.clone(), nan];
+        let set: HashSet<_> = values.iter().collect();
+        assert_eq!(set.len(), 3);
+
+        let mut values = vec![object! {}, array![], 1.into(), "a".into(), true.into(), JsonValue::Null];
+        values.sort();
+        assert_eq!(values, vec![JsonValue::Null, true.into(), "a".into(), 1.into(), array![], object! {}]);
+    }
+
+    #[test]
+    fn semantic_eq() {
+        let a = crate::parse(r#"{"n": [1, 1.0, 1e0, 0], "s": "x"}"#).unwrap();
+        let b = crate::parse(r#"{"s": "x", "n": [1.0, 1, 1, -0.0]}"#).unwrap();
+        assert_ne!(a, b);
+        assert!(a.semantic_eq(&b));
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert!(!array![1].semantic_eq(&array![1.5]));
+        assert!(!array![1].semantic_eq(&array![1, 1]));
+        assert!(!JsonValue::from(f64::NAN).semantic_eq(&f64::NAN.into()));
+
+        assert_eq!(JsonValue::Null.content_hash(), Ok(0x5b9bc4ba528108e4));
+        assert_ne!(array![1, 2].content_hash(), array![2, 1].content_hash());
+        let err = JsonValue::from(f64::NAN).content_hash().unwrap_err();
+        assert_eq!(err.to_string(), "invalid type: NaN has no json form");
+    }
+
+    #[test]
+    fn raw() {
+        let fragment = JsonValue::raw(" {\"z\": 1.50,\n \"a\": [] } ").unwrap();
+        let value = object! {"id" => 7, "body" => fragment.clone()};
+        assert_eq!(value.dump(), "{\"body\":{\"z\": 1.50,\n \"a\": [] },\"id\":7}");
+        assert!(value.pretty(2).contains("\"body\": {\"z\": 1.50,\n \"a\": [] },\n"));
+        assert_eq!(fragment.type_name(), "raw");
+        assert_eq!(
+            to_canonical_string(&fragment).unwrap(),
+            to_canonical_string(&crate::parse(r#"{"a":[],"z":1.5}"#).unwrap()).unwrap()
+        );
+
+        assert!(JsonValue::raw("[1,]").is_err());
+        assert!(JsonValue::raw("1 2").is_err());
+        assert!(JsonValue::raw("").is_err());
+        // unchecked, it goes out as it is
+        assert_eq!(array![JsonValue::Raw("nope".to_string())].dump(), "[nope]");
+    }
+}
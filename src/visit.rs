@@ -0,0 +1,362 @@
+use std::fmt::Write;
+
+use crate::value::JsonValue;
+
+// parents before their children, or after them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Order {
+    #[default]
+    Pre,
+    Post,
+}
+
+// what the walk does after visiting a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    Continue,
+    // leave the children of this node out, pre-order only
+    Skip,
+    // take the node out of its parent, `walk_mut` only (`walk` skips it). A removed
+    // root becomes null.
+    Remove,
+    // no more visits
+    Stop,
+}
+
+// Called for every node with its RFC 6901 pointer, "" for the root. Closures taking
+// `(&str, &JsonValue)` are visitors too.
+pub trait Visitor {
+    fn visit(&mut self, path: &str, value: &JsonValue) -> Walk;
+}
+
+// A visitor that may change nodes. Assigning to `value` replaces the node, and in
+// pre-order the walk goes on into the replacement.
+pub trait VisitorMut {
+    fn visit(&mut self, path: &str, value: &mut JsonValue) -> Walk;
+}
+
+impl<F: FnMut(&str, &JsonValue) -> Walk> Visitor for F {
+    fn visit(&mut self, path: &str, value: &JsonValue) -> Walk {
+        self(path, value)
+    }
+}
+
+impl<F: FnMut(&str, &mut JsonValue) -> Walk> VisitorMut for F {
+    fn visit(&mut self, path: &str, value: &mut JsonValue) -> Walk {
+        self(path, value)
+    }
+}
+
+pub fn walk<V: Visitor>(value: &JsonValue, order: Order, visitor: &mut V) {
+    walk_node(&mut String::new(), value, order, visitor);
+}
+
+pub fn walk_mut<V: VisitorMut>(value: &mut JsonValue, order: Order, visitor: &mut V) {
+    if walk_node_mut(&mut String::new(), value, order, visitor) == Walk::Remove {
+        *value = JsonValue::Null;
+    }
+}
+
+// `path` is the pointer of `value`, children append to it and put it back
+fn walk_node<V: Visitor>(
+    path: &mut String,
+    value: &JsonValue,
+    order: Order,
+    visitor: &mut V,
+) -> Walk {
+    if order == Order::Pre {
+        match visitor.visit(path, value) {
+            Walk::Continue => (),
+            Walk::Skip | Walk::Remove => return Walk::Continue,
+            Walk::Stop => return Walk::Stop,
+        }
+    }
+
+    let len = path.len();
+    let mut stops = |path: &mut String, child: &JsonValue| {
+        let flow = walk_node(path, child, order, visitor);
+        path.truncate(len);
+        flow == Walk::Stop
+    };
+    let stopped = match value {
+        JsonValue::Array(array) => array.iter().enumerate().any(|(i, child)| {
+            push_index(path, i);
+            stops(path, child)
+        }),
+        JsonValue::Object(object) => object.iter().any(|(key, child)| {
+            push_key(path, key);
+            stops(path, child)
+        }),
+        _ => false,
+    };
+    if stopped {
+        return Walk::Stop;
+    }
+
+    match order {
+        Order::Post if visitor.visit(path, value) == Walk::Stop => Walk::Stop,
+        _ => Walk::Continue,
+    }
+}
+
+// like `walk_node`, but a `Remove` goes back to the parent to act on
+fn walk_node_mut<V: VisitorMut>(
+    path: &mut String,
+    value: &mut JsonValue,
+    order: Order,
+    visitor: &mut V,
+) -> Walk {
+    if order == Order::Pre {
+        match visitor.visit(path, value) {
+            Walk::Continue => (),
+            Walk::Skip => return Walk::Continue,
+            flow => return flow,
+        }
+    }
+
+    let len = path.len();
+    let mut child = |path: &mut String, child: &mut JsonValue| {
+        let flow = walk_node_mut(path, child, order, visitor);
+        path.truncate(len);
+        flow
+    };
+    match value {
+        JsonValue::Array(array) => {
+            // removed items go in one pass at the end, the ones after them are visited
+            // at the index they move to
+            let mut keep = vec![true; array.len()];
+            let mut removed = 0;
+            let mut stopped = false;
+            for (i, item) in array.iter_mut().enumerate() {
+                push_index(path, i - removed);
+                match child(path, item) {
+                    Walk::Stop => {
+                        stopped = true;
+                        break;
+                    }
+                    Walk::Remove => {
+                        keep[i] = false;
+                        removed += 1;
+                    }
+                    _ => (),
+                }
+            }
+            if removed > 0 {
+                let mut keep = keep.into_iter();
+                array.retain(|_| keep.next().unwrap());
+            }
+            if stopped {
+                return Walk::Stop;
+            }
+        }
+        JsonValue::Object(object) => {
+            let mut removed = Vec::new();
+            let mut stopped = false;
+            for (key, value) in object.iter_mut() {
+                push_key(path, key);
+                match child(path, value) {
+                    Walk::Stop => {
+                        stopped = true;
+                        break;
+                    }
+                    Walk::Remove => removed.push(key.clone()),
+                    _ => (),
+                }
+            }
+            // removals before the stop still happen
+            for key in removed {
+                object.remove(&key);
+            }
+            if stopped {
+                return Walk::Stop;
+            }
+        }
+        _ => (),
+    }
+
+    match order {
+        Order::Post => match visitor.visit(path, value) {
+            Walk::Skip => Walk::Continue,
+            flow => flow,
+        },
+        Order::Pre => Walk::Continue,
+    }
+}
+
+// `/index` and `/key` onto a pointer, the key escaped as it is written
+fn push_index(path: &mut String, index: usize) {
+    write!(path, "/{}", index).unwrap();
+}
+
+fn push_key(path: &mut String, key: &str) {
+    path.push('/');
+    for ch in key.chars() {
+        match ch {
+            '~' => path.push_str("~0"),
+            '/' => path.push_str("~1"),
+            ch => path.push(ch),
+        }
+    }
+}
+
+// Every leaf with its pointer, in document order: scalars and empty arrays and objects.
+pub struct Leaves<'a> {
+    // nodes still to look at, the next one last
+    stack: Vec<(String, &'a JsonValue)>,
+}
+
+impl<'a> Leaves<'a> {
+    pub(crate) fn new(value: &'a JsonValue) -> Self {
+        Leaves {
+            stack: vec![(String::new(), value)],
+        }
+    }
+}
+
+impl<'a> Iterator for Leaves<'a> {
+    type Item = (String, &'a JsonValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (path, value) = self.stack.pop()?;
+            match value {
+                JsonValue::Array(array) if !array.is_empty() => {
+                    let children = array.iter().enumerate().rev();
+                    self.stack.extend(children.map(|(i, v)| {
+                        let mut path = path.clone();
+                        push_index(&mut path, i);
+                        (path, v)
+                    }));
+                }
+                JsonValue::Object(object) if !object.is_empty() => {
+                    let children = object.iter().rev();
+                    self.stack.extend(children.map(|(k, v)| {
+                        let mut path = path.clone();
+                        push_key(&mut path, k);
+                        (path, v)
+                    }));
+                }
+                _ => return Some((path, value)),
+            }
+        }
+    }
+}
+
+impl JsonValue {
+    pub fn leaves(&self) -> Leaves<'_> {
+        Leaves::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, json, object};
+
+    fn sample() -> JsonValue {
+        json!({"a": [1, {"b": null}], "c/d": "x", "e": {}})
+    }
+
+    #[test]
+    fn orders() {
+        let mut pre = Vec::new();
+        walk(&sample(), Order::Pre, &mut |path: &str, _: &JsonValue| {
+            pre.push(path.to_string());
+            Walk::Continue
+        });
+        assert_eq!(pre, ["", "/a", "/a/0", "/a/1", "/a/1/b", "/c~1d", "/e"]);
+
+        let mut post = Vec::new();
+        walk(&sample(), Order::Post, &mut |path: &str, _: &JsonValue| {
+            post.push(path.to_string());
+            Walk::Continue
+        });
+        assert_eq!(post, ["/a/0", "/a/1/b", "/a/1", "/a", "/c~1d", "/e", ""]);
+    }
+
+    #[test]
+    fn skip_and_stop() {
+        let mut seen = Vec::new();
+        walk(&sample(), Order::Pre, &mut |path: &str, _: &JsonValue| {
+            seen.push(path.to_string());
+            match path {
+                "/a" => Walk::Skip,
+                "/c~1d" => Walk::Stop,
+                _ => Walk::Continue,
+            }
+        });
+        assert_eq!(seen, ["", "/a", "/c~1d"]);
+    }
+
+    #[test]
+    fn replace_and_remove() {
+        // scrub strings, drop nulls and turn numbers into strings
+        let mut value = json!({"name": "ada", "ids": [1, null, 2, null], "n": null});
+        walk_mut(&mut value, Order::Pre, &mut |_: &str, value: &mut JsonValue| match value {
+            JsonValue::Null => Walk::Remove,
+            JsonValue::Number(n) => {
+                *value = format!("#{}", n).into();
+                Walk::Continue
+            }
+            JsonValue::String(s) if !s.starts_with('*') => {
+                *value = format!("*{}", s.len()).into();
+                Walk::Continue
+            }
+            _ => Walk::Continue,
+        });
+        assert_eq!(value, object! {"name" => "*3", "ids" => array!["#1", "#2"]});
+
+        // post-order sees the children already rewritten
+        let mut value = json!([[1, 2], [3]]);
+        walk_mut(&mut value, Order::Post, &mut |_: &str, value: &mut JsonValue| {
+            if let JsonValue::Array(items) = value {
+                let sum: f64 = items.iter().filter_map(|n| n.to_string().parse::<f64>().ok()).sum();
+                *value = sum.into();
+            }
+            Walk::Continue
+        });
+        assert_eq!(value, JsonValue::from(6.0));
+
+        let mut value = json!(1);
+        walk_mut(&mut value, Order::Pre, &mut |_: &str, _: &mut JsonValue| Walk::Remove);
+        assert_eq!(value, JsonValue::Null);
+
+        // keys removed before a stop are gone
+        let mut value = json!({"a": null, "b": 1, "c": null});
+        walk_mut(&mut value, Order::Pre, &mut |path: &str, value: &mut JsonValue| match value {
+            JsonValue::Null => Walk::Remove,
+            _ if path == "/b" => Walk::Stop,
+            _ => Walk::Continue,
+        });
+        assert_eq!(value, object! {"b" => 1, "c" => JsonValue::Null});
+
+        // items after a removed one are visited at the index they move to
+        let mut value = json!([null, 1, null, 2, 3]);
+        let mut seen = Vec::new();
+        walk_mut(&mut value, Order::Pre, &mut |path: &str, value: &mut JsonValue| {
+            seen.push(path.to_string());
+            match value {
+                JsonValue::Null => Walk::Remove,
+                _ if *value == JsonValue::from(2) => Walk::Stop,
+                _ => Walk::Continue,
+            }
+        });
+        assert_eq!(value, array![1, 2, 3]);
+        assert_eq!(seen, ["", "/0", "/0", "/1", "/1"]);
+    }
+
+    #[test]
+    fn leaves() {
+        let leaves: Vec<_> = sample().leaves().map(|(path, v)| (path, v.dump())).collect();
+        assert_eq!(
+            leaves,
+            [
+                ("/a/0".to_string(), "1".to_string()),
+                ("/a/1/b".to_string(), "null".to_string()),
+                ("/c~1d".to_string(), "\"x\"".to_string()),
+                ("/e".to_string(), "{}".to_string()),
+            ]
+        );
+        assert_eq!(JsonValue::from(1).leaves().count(), 1);
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt::Display;
+
+// A string that may hold unpaired surrogates, as JavaScript strings can.
+// Stored as WTF-8: utf-8 where a lone surrogate takes the 3-byte form utf-8 would
+// give it (ED A0..BF xx) and a surrogate pair is always joined into one 4-byte char.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Wtf8String {
+    bytes: Vec<u8>,
+}
+
+// a piece of a `Wtf8String`, see `Wtf8String::chunks`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wtf8Chunk<'a> {
+    Str(&'a str),
+    Surrogate(u16),
+}
+
+fn is_high(unit: u16) -> bool {
+    (0xD800..0xDC00).contains(&unit)
+}
+
+fn is_surrogate_at(bytes: &[u8], i: usize) -> bool {
+    bytes[i] == 0xED && bytes.get(i + 1).is_some_and(|&b| b >= 0xA0)
+}
+
+// the 3-byte form of a surrogate code unit
+pub(crate) fn encode_surrogate(unit: u16) -> [u8; 3] {
+    [
+        0xE0 | (unit >> 12) as u8,
+        0x80 | ((unit >> 6) & 0x3F) as u8,
+        0x80 | (unit & 0x3F) as u8,
+    ]
+}
+
+fn decode_surrogate(bytes: &[u8]) -> u16 {
+    0xD000 | ((bytes[1] as u16 & 0x3F) << 6) | (bytes[2] as u16 & 0x3F)
+}
+
+impl Wtf8String {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `bytes` must already be well-formed WTF-8, as the tokenizer builds it
+    pub(crate) fn from_wtf8_unchecked(bytes: Vec<u8>) -> Self {
+        Wtf8String { bytes }
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    pub fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]));
+    }
+
+    // push any utf-16 code unit, a low surrogate right after a high one makes a pair
+    pub fn push_code_unit(&mut self, unit: u16) {
+        if let Some(ch) = char::from_u32(unit as u32) {
+            return self.push(ch);
+        }
+        let len = self.bytes.len();
+        if !is_high(unit) && len >= 3 && is_surrogate_at(&self.bytes, len - 3) {
+            let high = decode_surrogate(&self.bytes[len - 3..]);
+            if is_high(high) {
+                self.bytes.truncate(len - 3);
+                let ch = char::decode_utf16([high, unit]).next().unwrap().unwrap();
+                return self.push(ch);
+            }
+        }
+        self.bytes.extend_from_slice(&encode_surrogate(unit));
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn has_surrogates(&self) -> bool {
+        (0..self.bytes.len()).any(|i| is_surrogate_at(&self.bytes, i))
+    }
+
+    // runs of valid utf-8 and the lone surrogates between them
+    pub fn chunks(&self) -> Wtf8Chunks<'_> {
+        Wtf8Chunks { bytes: &self.bytes }
+    }
+
+    // lone surrogates become U+FFFD
+    pub fn to_string_lossy(&self) -> String {
+        self.chunks()
+            .map(|chunk| match chunk {
+                Wtf8Chunk::Str(s) => s,
+                Wtf8Chunk::Surrogate(_) => "\u{FFFD}",
+            })
+            .collect()
+    }
+
+    pub fn into_string(self) -> Result<String, Wtf8String> {
+        match self.has_surrogates() {
+            true => Err(self),
+            // SAFETY: WTF-8 without surrogates is utf-8
+            false => Ok(unsafe { String::from_utf8_unchecked(self.bytes) }),
+        }
+    }
+}
+
+pub struct Wtf8Chunks<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Wtf8Chunks<'a> {
+    type Item = Wtf8Chunk<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        if is_surrogate_at(self.bytes, 0) {
+            let unit = decode_surrogate(self.bytes);
+            self.bytes = &self.bytes[3..];
+            return Some(Wtf8Chunk::Surrogate(unit));
+        }
+        let end = (0..self.bytes.len())
+            .find(|&i| is_surrogate_at(self.bytes, i))
+            .unwrap_or(self.bytes.len());
+        let (run, rest) = self.bytes.split_at(end);
+        self.bytes = rest;
+        // SAFETY: outside of surrogates WTF-8 is utf-8, and surrogates start on a char boundary
+        Some(Wtf8Chunk::Str(unsafe { std::str::from_utf8_unchecked(run) }))
+    }
+}
+
+impl From<&str> for Wtf8String {
+    fn from(s: &str) -> Self {
+        Wtf8String {
+            bytes: s.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl From<String> for Wtf8String {
+    fn from(s: String) -> Self {
+        Wtf8String {
+            bytes: s.into_bytes(),
+        }
+    }
+}
+
+impl Display for Wtf8String {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_string_lossy().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_units() {
+        let mut s = Wtf8String::from("a");
+        s.push_code_unit(0xD834);
+        s.push_code_unit(0xDD1E);
+        s.push_code_unit(0xDC00);
+        s.push('b');
+        s.push_code_unit(0xD800);
+        assert_eq!(s.as_bytes(), b"a\xF0\x9D\x84\x9E\xED\xB0\x80b\xED\xA0\x80");
+        assert_eq!(
+            s.chunks().collect::<Vec<_>>(),
+            vec![
+                Wtf8Chunk::Str("a𝄞"),
+                Wtf8Chunk::Surrogate(0xDC00),
+                Wtf8Chunk::Str("b"),
+                Wtf8Chunk::Surrogate(0xD800),
+            ]
+        );
+        assert_eq!(s.to_string_lossy(), "a𝄞\u{FFFD}b\u{FFFD}");
+        assert!(s.clone().into_string().is_err());
+        assert_eq!(Wtf8String::from("é").into_string(), Ok("é".to_string()));
+    }
+}
@@ -0,0 +1,5 @@
+use json::json;
+
+fn main() {
+    let _ = json!({"id": 1, "name" "ada"});
+}
@@ -0,0 +1,5 @@
+use json::json;
+
+fn main() {
+    let _ = json!([1, true null]);
+}
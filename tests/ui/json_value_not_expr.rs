@@ -0,0 +1,5 @@
+use json::json;
+
+fn main() {
+    let _ = json!({"a": 1 2});
+}